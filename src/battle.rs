@@ -1,304 +1,491 @@
-use ggez::graphics::{self, Color};
-use rand::{random};
-use std::cmp;
-
-use crate::palette;
-use crate::projector::{Projector};
-use crate::hud::action_frame::{ActionFrame};
-use crate::hud::action_hotbar;
-use crate::hud::action_timeline::{self, ActionTimeline};
-use crate::hud::resource_guage::{self, ResourceGuage};
-use crate::hud::balance_guage::{self, BalanceGuage};
-
-const PLAYER_MAX_FATIGUE: i32 = 100;
-const PLAYER_FIRST_ACTION: f32 = 50.0;
-const ENEMY_MAX_HP: i32 = 50;
-const ENEMY_FIRST_ACTION: f32 = 100.0;
-const ACTION_POINTS_PER_SECOND: f32 = 60.0;
-const ATTACK_DAMAGE: i32 = 10;
-const ATTACK_ACTION_TIME: f32 = 250.0;
-const ATTACK_FATIGUE_COST: i32 = 5;
-const BLOCK_ACTION_TIME: f32 = 300.0;
-const BLOCK_FATIGUE_COST: i32 = 5;
-const BLOCK_HIT_TIME_PENALTY: f32 = 100.0;
-
-struct Player {
-    color: Color,
-    max_fatigue: i32,
-    current_fatigue: i32,
-    current_balance: f32,
-    next_action_time: f32,
-    is_blocking: bool,
-    block_end_time: f32
+use rand::{Rng, SeedableRng};
+use rand::rngs::{StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::definitions::{EnemyDefinition, PlayerDefinition};
+
+pub mod ability;
+pub mod ai;
+pub mod combatant;
+pub mod item;
+pub mod replay;
+pub mod status;
+
+use ability::{AbilityDefinition, AbilityEffect, TargetKind};
+use ai::{EnemyAi, EnemyMove};
+use combatant::{Combatant, CombatantId};
+use item::{Inventory, ItemDrop};
+use replay::{Command, Replay};
+use status::{StatusChange, StatusDefinition, StatusKind};
+
+pub const ACTION_POINTS_PER_SECOND: f32 = 60.0;
+
+/// Balance an action is made at when it is neither weak nor strong.
+pub const ON_BALANCE: f32 = 0.3;
+
+/// Chance of escaping from enemies exactly as quick as the party.
+const ESCAPE_BASE_CHANCE: f32 = 0.5;
+const ESCAPE_MIN_CHANCE: f32 = 0.1;
+const ESCAPE_MAX_CHANCE: f32 = 0.95;
+/// Action time lost by a failed escape.
+const ESCAPE_ACTION_TIME: f32 = 200.0;
+
+pub struct Player {
+    pub combatant: Combatant,
+    abilities: Vec<AbilityDefinition>,
+    queued_action: Option<QueuedAction>
 }
 
-struct PlayerInBattle {
-    stats: Player,
-    fatigue_guage: ResourceGuage,
-    balance_guage: BalanceGuage,
-    timeline_handle: i32,
-    action_frame: ActionFrame
-}
-
-impl PlayerInBattle {
-    fn new(player: Player, timeline: &mut ActionTimeline) -> Self {
-        Self {
-            fatigue_guage: ResourceGuage::new(
-                player.max_fatigue as f32,
-                player.current_fatigue as f32,
-                player.color
+impl Player {
+    /// Starts with `fatigue` left, a player at 0 joins the battle already down.
+    fn new<R: Rng>(definition: &PlayerDefinition, fatigue: i32, rng: &mut R) -> Self {
+        let mut player = Self {
+            combatant: Combatant::new(
+                definition.name.clone(),
+                definition.color,
+                definition.max_fatigue,
+                definition.first_action,
+                definition.block_hit_time_penalty,
+                definition.on_hit.clone(),
+                rng
             ),
-            balance_guage: BalanceGuage::new(player.current_balance),
-            timeline_handle: timeline.add_subject(
-                player.color,
-                player.next_action_time
-            ),
-            action_frame: ActionFrame::new(player.color),
-            stats: player
-        }
+            abilities: definition.abilities.clone(),
+            queued_action: None
+        };
+
+        player.combatant.current_resource = fatigue.max(0).min(definition.max_fatigue);
+        player.combatant.set_block_damage_divisor(definition.block_damage_divisor);
+        player.combatant.set_balance_recovery(definition.balance_recovery);
+        player
     }
-}
 
-pub struct Enemy {
-    max_hp: i32,
-    pub current_hp: i32,
-    current_balance: f32,
-    next_action_time: f32
+    pub fn abilities(&self) -> &[AbilityDefinition] {
+        &self.abilities
+    }
+
+    pub fn queued_action(&self) -> Option<QueuedAction> {
+        self.queued_action
+    }
 }
 
-pub struct EnemyInBattle {
-    world_id: u32,
-    pub stats: Enemy,
-    hp_guage: ResourceGuage,
-    balance_guage: BalanceGuage,
-    pub timeline_handle: i32,
-    action_frame: ActionFrame
+pub struct Enemy {
+    pub world_id: u32,
+    /// Letter identifying this enemy on the HUD, the timeline and the overworld
+    pub marker: char,
+    pub combatant: Combatant,
+    attack_damage: i32,
+    attack_action_time: f32,
+    experience: u32,
+    drops: Vec<ItemDrop>,
+    ai: Box<dyn EnemyAi>,
+    intent: Option<EnemyMove>
 }
 
-impl EnemyInBattle {
-    fn new(enemy_id: u32, enemy: Enemy, timeline: &mut ActionTimeline) -> Self {
-        EnemyInBattle {
-            world_id: enemy_id,
-            hp_guage: ResourceGuage::new(
-                enemy.max_hp as f32,
-                enemy.current_hp as f32,
-                palette::RED
-            ),
-            balance_guage: BalanceGuage::new(enemy.current_balance),
-            timeline_handle: timeline.add_subject(
-                palette::RED,
-                enemy.next_action_time
+impl Enemy {
+    fn new<R: Rng>(world_id: u32, marker: char, definition: &EnemyDefinition, rng: &mut R) -> Self {
+        Self {
+            world_id: world_id,
+            marker: marker,
+            combatant: Combatant::new(
+                format!("{} {}", definition.name, marker),
+                definition.color,
+                definition.max_hp,
+                definition.first_action,
+                definition.block_hit_time_penalty,
+                definition.on_hit.clone(),
+                rng
             ),
-            stats: enemy,
-            action_frame: ActionFrame::new(palette::RED)
+            attack_damage: definition.attack_damage,
+            attack_action_time: definition.attack_action_time,
+            experience: definition.experience,
+            drops: definition.drops.clone(),
+            ai: ai::create(&definition.ai),
+            intent: None
         }
     }
+
+    pub fn drops(&self) -> &[ItemDrop] {
+        &self.drops
+    }
+
+    /// What the enemy will do on its next turn, shown to the players so they
+    /// can prepare. None only once there is nobody left to act against.
+    pub fn intent(&self) -> Option<EnemyMove> {
+        self.intent
+    }
 }
 
+/// The rules of a battle with no knowledge of how it is drawn. Every change
+/// is reported through `BattleEvents` so a view can follow along.
 pub struct BattleState {
+    rng: StdRng,
     action_time: f32,
-    pub timeline: ActionTimeline,
-    players: Vec<PlayerInBattle>,
+    players: Vec<Player>,
     players_pending: Vec<usize>,
-    pub enemies: Vec<EnemyInBattle>,
-    pub hovered_enemy: Option<usize>,
-    pub target_enemy: usize
+    enemies: Vec<Enemy>,
+    target_enemy: usize,
+    inventory: Inventory,
+    clock_mode: ClockMode,
+    outcome: Option<Outcome>,
+    replay: Replay
 }
 
 impl BattleState {
 
-    pub fn handle_mouse_move(&mut self, x: f32, y: f32, projector: &Projector) {
-        self.timeline.highlighted_subject = None;
-        self.hovered_enemy = None;
+    pub fn tick<F: FnMut(BattleEvents)>(&mut self, delta: f32, mut notify: F) {
 
-        if y < projector.scale(70.0) {
-            for (i, enemy) in self.enemies.iter().enumerate().rev() {
-                if projector.top_right((i + 1) as f32 * 140.0).to_local_x(x) > 0.0 && enemy.stats.current_hp > 0 {
-                    self.hovered_enemy = Some(i);
-                    self.timeline.highlighted_subject = Some(enemy.timeline_handle);
-                }
-            }
+        if self.outcome.is_some() {
+            return;
         }
-    }
 
-    pub fn tick<F: FnMut(BattleEvents)>(&mut self, delta: f32, mut notify: F) {
+        if self.clock_mode != ClockMode::Active && self.player_move_pending() {
+            return;
+        }
 
-        self.action_time += ACTION_POINTS_PER_SECOND * delta;
-        self.timeline.update(self.action_time);
+        self.replay.record_tick(delta);
 
-        for enemy in &mut self.enemies {
+        match self.clock_mode {
+            ClockMode::Turns => {
+                // Land on the first whole action point past the next turn, as the running clock would
+                if let Some(next_turn_time) = self.next_turn_time() {
+                    self.action_time = self.action_time.max(next_turn_time.floor() + 1.0);
+                }
+            },
+            _ => self.action_time += ACTION_POINTS_PER_SECOND * delta
+        }
 
-            enemy.action_frame.update_time(self.action_time / ACTION_POINTS_PER_SECOND);
+        self.update_statuses(&mut notify);
 
-            if enemy.stats.current_hp > 0 && self.action_time > enemy.stats.next_action_time {
+        for i in 0..self.enemies.len() {
+            let enemy = &self.enemies[i].combatant;
 
-                // Enemy attack
-                let mut target_player_index = 0;
-                while self.players.len() > target_player_index + 1 &&
-                    self.players[target_player_index].stats.current_fatigue == 0 {
-                    target_player_index += 1;
-                }
-                let target_player = &mut self.players[target_player_index];
+            if enemy.is_standing() && self.action_time > enemy.next_action_time && self.outcome.is_none() {
+                self.enemy_turn(i, &mut notify);
+            }
+        }
 
-                let mut dmg = calculate_balance_dmg(ATTACK_DAMAGE, enemy.stats.current_balance);
+        for (i, player) in self.players.iter().enumerate() {
+            let player = &player.combatant;
 
-                if target_player.stats.is_blocking {
-                    dmg = dmg / 4;
-                    target_player.stats.next_action_time += BLOCK_HIT_TIME_PENALTY;
-                    target_player.action_frame.activate("Block");
-                    self.timeline.update_subject(target_player.timeline_handle, target_player.stats.next_action_time);
+            if player.is_standing() && self.action_time > player.next_action_time {
+                // Queue up player for attack
+                if !has_item(&self.players_pending, &i) {
+                    self.players_pending.push(i);
                 }
+            }
+        }
 
-                target_player.stats.current_fatigue -= dmg;
-                target_player.stats.current_fatigue = cmp::max(0, target_player.stats.current_fatigue);
-                notify(BattleEvents::PlayerTakesDamage(target_player_index));
-
-                enemy.stats.current_balance = calculate_balance();
-                enemy.stats.next_action_time = self.action_time + ATTACK_ACTION_TIME;
+        self.take_queued_actions(&mut notify);
 
-                enemy.action_frame.activate("Attack");
-                enemy.balance_guage.update(enemy.stats.current_balance);
+        // Blocks last through the turns taken on the tick they end
+        let action_time = self.action_time;
 
-                self.timeline.update_subject(enemy.timeline_handle, enemy.stats.next_action_time);
-            }
+        for id in self.combatant_ids() {
+            self.combatant_mut(id).end_expired_block(action_time);
         }
+    }
 
-        for (i, player) in self.players.iter_mut().enumerate() {
+    /// Players with an action already chosen take it as soon as they are
+    /// ready, even behind someone still deciding, so the battle never waits
+    /// on one. An action that can no longer be used is dropped and the player
+    /// is left to choose. Runs after anything that can make a player ready.
+    fn take_queued_actions<F: FnMut(BattleEvents)>(&mut self, notify: &mut F) {
+        let mut pending_index = 0;
+
+        while pending_index < self.players_pending.len() && self.outcome.is_none() {
+            let i = self.players_pending[pending_index];
+
+            let queued = match self.players[i].queued_action.take() {
+                Some(queued) => queued,
+                None => {
+                    pending_index += 1;
+                    continue;
+                }
+            };
 
-            player.action_frame.update_time(self.action_time / ACTION_POINTS_PER_SECOND);
+            let chosen_target = self.target_enemy;
 
-            if player.stats.current_fatigue > 0 && self.action_time > player.stats.next_action_time {
-                // Queue up player for attack
-                if !has_item(&self.players_pending, &i) {
-                    self.players_pending.push(i);
-                }
+            if self.enemies[queued.target_enemy].combatant.is_standing() {
+                self.target_enemy = queued.target_enemy;
             }
 
-            if player.stats.is_blocking && self.action_time > player.stats.block_end_time {
-                player.stats.is_blocking = false;
+            let used = match queued.action {
+                PlayerAction::Ability(slot) => self.use_ability(pending_index, slot, false, notify),
+                PlayerAction::Item(index) => self.use_item(pending_index, index, false, notify)
+            };
+
+            if self.enemies[chosen_target].combatant.is_standing() {
+                self.target_enemy = chosen_target;
             }
 
-            balance_guage::update(&mut player.balance_guage, delta);
-            resource_guage::update(&mut player.fatigue_guage, delta);
+            // Acting takes the player out of the queue, anyone after them moves up
+            if !used {
+                pending_index += 1;
+            }
         }
+    }
 
-        for enemy in &mut self.enemies {
-            balance_guage::update(&mut enemy.balance_guage, delta);
-            resource_guage::update(&mut enemy.hp_guage, delta);
+    /// Carries out the move the enemy chose when this turn was scheduled,
+    /// then chooses the next one.
+    fn enemy_turn<F: FnMut(BattleEvents)>(&mut self, enemy_index: usize, notify: &mut F) {
+        let intent = match self.enemies[enemy_index].intent.take() {
+            Some(intent) => intent,
+            None => return
+        };
+
+        match intent {
+            EnemyMove::Attack(target_player_index) => self.enemy_attack(enemy_index, target_player_index, notify),
+            EnemyMove::Block => self.enemy_block(enemy_index, notify)
         }
 
+        if self.outcome.is_none() {
+            self.choose_enemy_intent(enemy_index);
+            notify(BattleEvents::EnemyIntent(enemy_index));
+        }
     }
 
-    pub fn handle_event<F: FnMut(BattleEvents)>(&mut self, event: &BattleEvents, mut notify: F) {
+    fn choose_enemy_intent(&mut self, enemy_index: usize) {
+        let enemy = &self.enemies[enemy_index];
 
-        match event {
-            BattleEvents::End(_) => {},
-            BattleEvents::EnemyDown(_) => {},
-            BattleEvents::EnemyTakesDamage(i) => {
-                let enemy = &mut self.enemies[*i];
+        let intent = if self.any_surviving_players() {
+            Some(enemy.ai.choose_move(enemy, &self.players, &mut self.rng))
+        } else {
+            None
+        };
 
-                enemy.hp_guage.update(enemy.stats.current_hp as f32);
+        self.enemies[enemy_index].intent = intent;
+    }
+
+    fn enemy_block<F: FnMut(BattleEvents)>(&mut self, enemy_index: usize, notify: &mut F) {
+        let enemy = &mut self.enemies[enemy_index];
 
-                if enemy.stats.current_hp == 0 {
-                    self.timeline.remove_subject(enemy.timeline_handle);
+        // Enemies have no separate block timing, a block lasts as long as an attack would
+        enemy.combatant.schedule(self.action_time, enemy.attack_action_time, &mut self.rng);
+        enemy.combatant.block();
+        notify(BattleEvents::Blocks(CombatantId::Enemy(enemy_index)));
+    }
 
-                    self.target_enemy = 0;
+    fn enemy_attack<F: FnMut(BattleEvents)>(&mut self, enemy_index: usize, target_player_index: usize, notify: &mut F) {
+        let enemy = &mut self.enemies[enemy_index];
 
-                    notify(BattleEvents::EnemyDown(enemy.world_id));
+        let attack_damage = enemy.attack_damage;
+        let balance = enemy.combatant.current_balance;
+        let on_hit = enemy.combatant.on_hit().cloned();
 
-                    while self.target_enemy < self.enemies.len() &&
-                          self.enemies[self.target_enemy].stats.current_hp == 0 {
-                        self.target_enemy += 1;
-                    }
+        enemy.combatant.schedule(self.action_time, enemy.attack_action_time, &mut self.rng);
+        notify(BattleEvents::EnemyAttacks(enemy_index));
+
+        self.hit(
+            CombatantId::Enemy(enemy_index),
+            CombatantId::Player(target_player_index),
+            attack_damage,
+            balance,
+            on_hit.as_ref(),
+            notify
+        );
+    }
+
+    /// Lands an attack of `raw` damage made at `balance` on `target`, blocking
+    /// it if they are blocking.
+    fn hit<F: FnMut(BattleEvents)>(
+        &mut self,
+        attacker: CombatantId,
+        target: CombatantId,
+        raw: i32,
+        balance: f32,
+        on_hit: Option<&StatusDefinition>,
+        notify: &mut F
+    ) {
+        let dmg = calculate_balance_dmg(raw, balance);
+        let (amount, blocked) = self.combatant_mut(target).receive_hit(dmg);
+
+        if blocked {
+            notify(BattleEvents::BlocksHit(target));
+        }
+
+        notify(BattleEvents::Damage(DamageReport {
+            source: DamageSource::Attack(attacker),
+            target: target,
+            raw: raw,
+            balance_multiplier: if raw > 0 { dmg as f32 / raw as f32 } else { 1.0 },
+            block_reduction: dmg - amount,
+            amount: amount
+        }));
+
+        self.damage_combatant(target, amount, notify);
+
+        // A blocked hit doesn't land its status
+        if let Some(status) = on_hit {
+            if !blocked && self.combatant(target).is_standing() {
+                self.apply_status(target, status, notify);
+            }
+        }
+    }
+
+    fn update_statuses<F: FnMut(BattleEvents)>(&mut self, notify: &mut F) {
+        let action_time = self.action_time;
+
+        for id in self.combatant_ids() {
+            if !self.combatant(id).is_standing() || self.outcome.is_some() {
+                continue;
+            }
+
+            for (kind, amount) in self.combatant_mut(id).statuses.take_ticks(action_time) {
+                // Going down clears statuses, any ticks still to come with them
+                if !self.combatant(id).is_standing() {
+                    break;
                 }
 
-                if self.target_enemy == self.enemies.len() {
-                    notify(BattleEvents::End(true));
+                match kind {
+                    StatusKind::Poison => {
+                        notify(BattleEvents::Damage(DamageReport {
+                            source: DamageSource::Status(kind),
+                            target: id,
+                            raw: amount,
+                            balance_multiplier: 1.0,
+                            block_reduction: 0,
+                            amount: amount
+                        }));
+                        self.damage_combatant(id, amount, notify);
+                    },
+                    StatusKind::Regen => self.restore_combatant(id, amount, notify),
+                    _ => {}
                 }
+            }
+
+            for kind in self.combatant_mut(id).statuses.take_expired(action_time) {
+                notify(BattleEvents::StatusExpired(id, kind));
+            }
+        }
+    }
+
+    fn apply_status<F: FnMut(BattleEvents)>(&mut self, id: CombatantId, definition: &StatusDefinition, notify: &mut F) {
+        let action_time = self.action_time;
+        let combatant = self.combatant_mut(id);
+
+        match combatant.statuses.apply(definition, action_time) {
+            StatusChange::Applied => {
+                if definition.kind == StatusKind::Stun {
+                    // A combatant already waiting to act loses its turn until the stun is served
+                    combatant.next_action_time = combatant.next_action_time.max(action_time) + definition.potency;
+
+                    if let CombatantId::Player(i) = id {
+                        self.players_pending.retain(|&j| j != i);
+                    }
+                }
+
+                notify(BattleEvents::StatusApplied(id, definition.kind));
             },
-            BattleEvents::PlayerTakesDamage(i) => {
-                let player = &mut self.players[*i];
+            StatusChange::Cancelled(kind) => notify(BattleEvents::StatusExpired(id, kind)),
+            StatusChange::Ignored => {}
+        }
+    }
+
+    /// Applies damage already reported by the caller and handles it taking `id` out.
+    fn damage_combatant<F: FnMut(BattleEvents)>(&mut self, id: CombatantId, amount: i32, notify: &mut F) {
+        self.combatant_mut(id).take_damage(amount);
+
+        if self.combatant(id).is_standing() {
+            return;
+        }
 
-                player.fatigue_guage.update(player.stats.current_fatigue as f32);
+        notify(BattleEvents::Down(id));
 
-                if player.stats.current_fatigue == 0 {
-                    self.players_pending.retain(|j| j != i);
-                    self.timeline.remove_subject(player.timeline_handle);
+        match id {
+            CombatantId::Player(i) => {
+                self.players_pending.retain(|&j| j != i);
+                self.players[i].queued_action = None;
 
-                    if !self.any_surviving_players() {
-                        notify(BattleEvents::End(false));
+                if !self.any_surviving_players() {
+                    self.end(Outcome::Defeat, notify);
+                    return;
+                }
+
+                // Enemies going for the fallen player pick something else to do
+                for j in 0..self.enemies.len() {
+                    if self.enemies[j].combatant.is_standing() && self.enemies[j].intent == Some(EnemyMove::Attack(i)) {
+                        self.choose_enemy_intent(j);
+                        notify(BattleEvents::EnemyIntent(j));
                     }
                 }
+            },
+            CombatantId::Enemy(_) => {
+                self.target_enemy = 0;
+
+                while self.target_enemy < self.enemies.len() &&
+                      !self.enemies[self.target_enemy].combatant.is_standing() {
+                    self.target_enemy += 1;
+                }
+
+                if self.target_enemy == self.enemies.len() {
+                    self.end(Outcome::Victory, notify);
+                }
             }
         }
     }
 
-    pub fn new(enemy_id: u32) -> Self {
-        let mut timeline = ActionTimeline::new();
+    fn restore_combatant<F: FnMut(BattleEvents)>(&mut self, id: CombatantId, amount: i32, notify: &mut F) {
+        self.combatant_mut(id).restore(amount);
+        notify(BattleEvents::Recovers(id, amount));
+    }
 
-        Self {
+    fn end<F: FnMut(BattleEvents)>(&mut self, outcome: Outcome, notify: &mut F) {
+        // A final blow can also exhaust the attacker, only the first outcome counts
+        if self.outcome.is_none() {
+            self.outcome = Some(outcome);
+            self.replay.outcome = Some(outcome);
+            notify(BattleEvents::End(outcome));
+        }
+    }
+
+    /// `party_fatigue` is what each party member has left coming into the battle.
+    pub fn new(
+        seed: u64,
+        party: &[PlayerDefinition],
+        party_fatigue: &[i32],
+        inventory: &Inventory,
+        enemy_id: u32,
+        enemy: &EnemyDefinition
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let players = party.iter()
+            .zip(party_fatigue)
+            .map(|(definition, fatigue)| Player::new(definition, *fatigue, &mut rng))
+            .collect();
+
+        let enemies = vec![
+            Enemy::new(enemy_id, enemy_marker(0), enemy, &mut rng)
+        ];
+
+        let mut battle = Self {
+            rng: rng,
             action_time: 0.0,
-            players: vec![
-                PlayerInBattle::new(
-                    Player {
-                        color: palette::GREEN,
-                        max_fatigue: PLAYER_MAX_FATIGUE,
-                        current_fatigue: PLAYER_MAX_FATIGUE,
-                        current_balance: calculate_balance(),
-                        next_action_time: PLAYER_FIRST_ACTION,
-                        is_blocking: false,
-                        block_end_time: 0.0
-                    },
-                    &mut timeline
-                ),
-                PlayerInBattle::new(
-                    Player {
-                        color: palette::BLUE,
-                        max_fatigue: PLAYER_MAX_FATIGUE,
-                        current_fatigue: PLAYER_MAX_FATIGUE,
-                        current_balance: calculate_balance(),
-                        next_action_time: PLAYER_FIRST_ACTION,
-                        is_blocking: false,
-                        block_end_time: 0.0
-                    },
-                    &mut timeline
-                )
-            ],
+            players: players,
             players_pending: Vec::new(),
-            enemies: vec![
-                EnemyInBattle::new(
-                    enemy_id,
-                    Enemy {
-                        max_hp: ENEMY_MAX_HP,
-                        current_hp: ENEMY_MAX_HP,
-                        current_balance: calculate_balance(),
-                        next_action_time: ENEMY_FIRST_ACTION
-                    },
-                    &mut timeline
-                )
-            ],
-            hovered_enemy: None,
+            enemies: enemies,
             target_enemy: 0,
-            timeline: timeline
-        }
+            inventory: inventory.clone(),
+            clock_mode: ClockMode::Active,
+            outcome: None,
+            replay: Replay::new(seed, party, party_fatigue, inventory, enemy_id, enemy)
+        };
+
+        battle.choose_enemy_intent(0);
+        battle
     }
 
-    pub fn add_enemy(&mut self, enemy_id: u32) {
-        self.enemies.push(EnemyInBattle::new(
-            enemy_id,
-            Enemy {
-                max_hp: ENEMY_MAX_HP,
-                current_hp: ENEMY_MAX_HP,
-                current_balance: calculate_balance(),
-                next_action_time: ENEMY_FIRST_ACTION
-            },
-            &mut self.timeline
-        ));
+    pub fn add_enemy<F: FnMut(BattleEvents)>(&mut self, enemy_id: u32, enemy: &EnemyDefinition, mut notify: F) {
+        self.replay.record_command(self.action_time, Command::AddEnemy(enemy_id, enemy.clone()));
+        let marker = enemy_marker(self.enemies.len());
+        self.enemies.push(Enemy::new(enemy_id, marker, enemy, &mut self.rng));
+        self.choose_enemy_intent(self.enemies.len() - 1);
+        notify(BattleEvents::EnemyJoins(self.enemies.len() - 1));
     }
 
-    fn any_surviving_players(&mut self) -> bool{
+    fn any_surviving_players(&self) -> bool {
         for player in &self.players {
-            if player.stats.current_fatigue > 0 {
+            if player.combatant.is_standing() {
                 return true;
             }
         }
@@ -306,108 +493,479 @@ impl BattleState {
         false
     }
 
+    /// When the next standing combatant is due to act.
+    fn next_turn_time(&self) -> Option<f32> {
+        self.combatant_ids().into_iter()
+            .map(|id| self.combatant(id))
+            .filter(|combatant| combatant.is_standing())
+            .map(|combatant| combatant.next_action_time)
+            .fold(None, |earliest: Option<f32>, time| Some(earliest.map_or(time, |earliest| earliest.min(time))))
+    }
+
+    /// Players first, then enemies, in the order they joined.
+    fn combatant_ids(&self) -> Vec<CombatantId> {
+        (0..self.players.len()).map(CombatantId::Player)
+            .chain((0..self.enemies.len()).map(CombatantId::Enemy))
+            .collect()
+    }
+
+    pub fn combatant(&self, id: CombatantId) -> &Combatant {
+        match id {
+            CombatantId::Player(i) => &self.players[i].combatant,
+            CombatantId::Enemy(i) => &self.enemies[i].combatant
+        }
+    }
+
+    fn combatant_mut(&mut self, id: CombatantId) -> &mut Combatant {
+        match id {
+            CombatantId::Player(i) => &mut self.players[i].combatant,
+            CombatantId::Enemy(i) => &mut self.enemies[i].combatant
+        }
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn enemies(&self) -> &[Enemy] {
+        &self.enemies
+    }
+
+    pub fn action_time(&self) -> f32 {
+        self.action_time
+    }
+
+    /// Experience for every enemy taken down so far.
+    pub fn experience_earned(&self) -> u32 {
+        self.enemies.iter()
+            .filter(|enemy| !enemy.combatant.is_standing())
+            .map(|enemy| enemy.experience)
+            .sum()
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn clock_mode(&self) -> ClockMode {
+        self.clock_mode
+    }
+
+    pub fn set_clock_mode(&mut self, clock_mode: ClockMode) {
+        if clock_mode != self.clock_mode {
+            self.replay.record_command(self.action_time, Command::ClockMode(clock_mode));
+            self.clock_mode = clock_mode;
+        }
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn target_enemy(&self) -> usize {
+        self.target_enemy
+    }
+
+    pub fn set_target_enemy(&mut self, i: usize) {
+        if i < self.enemies.len() && self.enemies[i].combatant.is_standing() && i != self.target_enemy {
+            self.replay.record_command(self.action_time, Command::Target(i));
+            self.target_enemy = i;
+        }
+    }
+
+    pub fn apply_command<F: FnMut(BattleEvents)>(&mut self, command: &Command, notify: F) {
+        match command {
+            Command::UseAbility(slot) => if self.player_move_pending() {
+                self.player_use_ability(*slot, notify);
+            },
+            Command::UseItem(index) => if self.player_move_pending() {
+                self.player_use_item(*index, notify);
+            },
+            Command::Escape => if self.player_move_pending() {
+                self.player_escape(notify);
+            },
+            Command::QueueAction(i, action) => self.queue_action(*i, *action),
+            Command::CancelQueuedAction(i) => self.cancel_queued_action(*i),
+            Command::Target(i) => self.set_target_enemy(*i),
+            Command::ClockMode(clock_mode) => self.set_clock_mode(*clock_mode),
+            Command::AddEnemy(enemy_id, enemy) => self.add_enemy(*enemy_id, enemy, notify)
+        }
+    }
+
     pub fn player_move_pending(&self) -> bool {
-        self.players_pending.len() > 0
+        self.players_pending.len() > 0 && self.outcome.is_none()
     }
 
-    pub fn player_attack_move<F: FnMut(BattleEvents)>(&mut self, mut notify: F) {
-        let attacking_player_index = self.players_pending.remove(0);
-        let attacking_player = &mut self.players[attacking_player_index];
-        attacking_player.stats.next_action_time = self.action_time + ATTACK_ACTION_TIME;
-        let dmg = calculate_balance_dmg(ATTACK_DAMAGE, attacking_player.stats.current_balance);
-        let enemy = &mut self.enemies[self.target_enemy];
-        enemy.stats.current_hp -= dmg;
-        enemy.stats.current_hp = cmp::max(0, enemy.stats.current_hp);
-        notify(BattleEvents::EnemyTakesDamage(self.target_enemy));
+    pub fn pending_player(&self) -> Option<usize> {
+        self.players_pending.first().cloned()
+    }
 
-        attacking_player.stats.current_fatigue -= ATTACK_FATIGUE_COST;
-        attacking_player.stats.current_balance = calculate_balance();
+    /// Hotbar slots hold a player's abilities followed by the party's items.
+    pub fn hotbar_action(&self, player_index: usize, slot: usize) -> PlayerAction {
+        let ability_count = self.players[player_index].abilities.len();
 
-        notify(BattleEvents::PlayerTakesDamage(attacking_player_index));
-        attacking_player.balance_guage.update(attacking_player.stats.current_balance);
+        if slot < ability_count {
+            PlayerAction::Ability(slot)
+        } else {
+            PlayerAction::Item(slot - ability_count)
+        }
+    }
 
-        attacking_player.action_frame.activate("Attack");
-        self.timeline.update_subject(attacking_player.timeline_handle, attacking_player.stats.next_action_time);
+    pub fn player_use_hotbar<F: FnMut(BattleEvents)>(&mut self, slot: usize, notify: F) {
+        match self.pending_player().map(|i| self.hotbar_action(i, slot)) {
+            Some(PlayerAction::Ability(slot)) => {
+                self.player_use_ability(slot, notify);
+            },
+            Some(PlayerAction::Item(index)) => self.player_use_item(index, notify),
+            None => {}
+        }
     }
 
-    pub fn player_block_move<F: FnMut(BattleEvents)>(&mut self, mut notify: F) {
-        let attacking_player_index = self.players_pending.remove(0);
-        let attacking_player = &mut self.players[attacking_player_index];
-        attacking_player.stats.next_action_time = self.action_time + BLOCK_ACTION_TIME;
+    /// When the player would next act after taking `action`, on their turn
+    /// if it hasn't come yet. None if there is no such action.
+    pub fn preview_next_action_time(&self, player_index: usize, action: PlayerAction) -> Option<f32> {
+        let player = &self.players[player_index];
 
-        attacking_player.stats.is_blocking = true;
-        attacking_player.stats.block_end_time = attacking_player.stats.next_action_time;
+        let action_time = match action {
+            PlayerAction::Ability(slot) => player.abilities.get(slot)?.action_time,
+            PlayerAction::Item(index) => self.inventory.slots().get(index)?.item.action_time
+        };
 
-        attacking_player.stats.current_fatigue -= BLOCK_FATIGUE_COST;
-        attacking_player.stats.current_balance = calculate_balance();
+        let start = player.combatant.next_action_time.max(self.action_time);
 
-        notify(BattleEvents::PlayerTakesDamage(attacking_player_index));
-        attacking_player.balance_guage.update(attacking_player.stats.current_balance);
+        Some(start + action_time * player.combatant.statuses.action_time_multiplier())
+    }
+
+    /// Players can have their next action chosen ahead of time, except the
+    /// one already being asked for it.
+    pub fn can_queue_action(&self, player_index: usize) -> bool {
+        self.outcome.is_none() &&
+            self.players[player_index].combatant.is_standing() &&
+            self.pending_player() != Some(player_index)
+    }
+
+    /// Chooses what a player does when their turn comes, aimed at the enemy
+    /// targeted right now. Replaces anything already queued for them.
+    pub fn queue_action(&mut self, player_index: usize, action: PlayerAction) {
+        let exists = match action {
+            PlayerAction::Ability(slot) => slot < self.players[player_index].abilities.len(),
+            PlayerAction::Item(index) => index < self.inventory.slots().len()
+        };
+
+        if exists && self.can_queue_action(player_index) {
+            self.replay.record_command(self.action_time, Command::QueueAction(player_index, action));
+            self.players[player_index].queued_action = Some(QueuedAction {
+                action: action,
+                target_enemy: self.target_enemy
+            });
+        }
+    }
 
-        self.timeline.update_subject(attacking_player.timeline_handle, attacking_player.stats.next_action_time);
+    pub fn cancel_queued_action(&mut self, player_index: usize) {
+        if self.players[player_index].queued_action.take().is_some() {
+            self.replay.record_command(self.action_time, Command::CancelQueuedAction(player_index));
+        }
     }
 
-    pub fn draw(&mut self, ctx: &mut ggez::Context, projector: &Projector) -> ggez::GameResult {
+    /// Uses the pending player's ability in hotbar `slot`. Slots the player
+    /// has no ability for are ignored, as are abilities with nobody to target.
+    /// Returns whether the ability was used.
+    pub fn player_use_ability<F: FnMut(BattleEvents)>(&mut self, slot: usize, mut notify: F) -> bool {
+        let used = self.use_ability(0, slot, true, &mut notify);
 
-        for (i, enemy) in self.enemies.iter_mut().enumerate() {
-            draw_enemy_display(
-                ctx,
-                enemy,
-                // -20.0 is to remove the final right margin included in the 140.0
-                &projector.top_right((i + 1) as f32 * 140.0 - 20.0),
-                i == self.target_enemy
-            )?;
+        if used {
+            self.take_queued_actions(&mut notify);
         }
 
-        if self.players_pending.len() > 0 {
-            action_hotbar::draw(
-                ctx,
-                &projector.bottom_left(150.0).centered_horizontal(490.0)
-            )?;
+        used
+    }
+
+    /// Used by whoever is at `pending_index` in the queue. Queued actions are
+    /// replayed by the battle itself so only commands given directly are
+    /// recorded. Returns whether the ability was used.
+    fn use_ability<F: FnMut(BattleEvents)>(&mut self, pending_index: usize, slot: usize, record: bool, notify: &mut F) -> bool {
+        let caster_index = match self.players_pending.get(pending_index) {
+            Some(&i) if slot < self.players[i].abilities.len() => i,
+            _ => return false
+        };
+
+        let ability = self.players[caster_index].abilities[slot].clone();
+        let targets = self.targets(caster_index, ability.target);
+
+        if targets.is_empty() {
+            return false;
         }
 
-        let mut player_display_offset = 0.0;
-        for (i, player) in self.players.iter_mut().enumerate() {
-            draw_player_display(
-                ctx,
-                player,
-                &projector.bottom_left(90.0)
-                    .local_relative(player_display_offset, 0.0),
-                self.players_pending.len() > 0 && self.players_pending[0] == i
-            )?;
-            player_display_offset += 140.0;
+        if record {
+            self.replay.record_command(self.action_time, Command::UseAbility(slot));
         }
 
-        let timeline_mesh = action_timeline::create_mesh(
-            ctx,
-            &self.timeline,
-            &projector.local()
-        )?;
+        self.players_pending.remove(pending_index);
+
+        let caster_id = CombatantId::Player(caster_index);
+        let balance = self.players[caster_index].combatant.current_balance;
+
+        self.players[caster_index].combatant.schedule(self.action_time, ability.action_time, &mut self.rng);
+        notify(BattleEvents::PlayerUsesAbility(caster_index, slot));
 
-        graphics::draw(
-            ctx,
-            &timeline_mesh,
-            (projector.bottom_left(170.0).centered_horizontal(400.0).origin(),)
-        )?;
+        self.apply_effects(caster_index, balance, ability.action_time, &targets, &ability.effects, notify);
 
-        Ok(())
+        notify(BattleEvents::FatigueCost(caster_id, ability.fatigue_cost));
+        self.damage_combatant(caster_id, ability.fatigue_cost, notify);
+        true
     }
+
+    /// Has the pending player use the item in inventory slot `index`. Items
+    /// that have run out or have nobody to target are ignored.
+    pub fn player_use_item<F: FnMut(BattleEvents)>(&mut self, index: usize, mut notify: F) {
+        if self.use_item(0, index, true, &mut notify) {
+            self.take_queued_actions(&mut notify);
+        }
+    }
+
+    /// See `use_ability` for `pending_index` and `record`. Returns whether the item was used.
+    fn use_item<F: FnMut(BattleEvents)>(&mut self, pending_index: usize, index: usize, record: bool, notify: &mut F) -> bool {
+        let user_index = match self.players_pending.get(pending_index) {
+            Some(&i) if self.inventory.has(index) => i,
+            _ => return false
+        };
+
+        let targets = self.targets(user_index, self.inventory.slots()[index].item.target);
+
+        if targets.is_empty() {
+            return false;
+        }
+
+        if record {
+            self.replay.record_command(self.action_time, Command::UseItem(index));
+        }
+
+        self.players_pending.remove(pending_index);
+
+        let item = self.inventory.take(index);
+        let balance = self.players[user_index].combatant.current_balance;
+
+        self.players[user_index].combatant.schedule(self.action_time, item.action_time, &mut self.rng);
+        notify(BattleEvents::PlayerUsesItem(user_index, index));
+
+        self.apply_effects(user_index, balance, item.action_time, &targets, &item.effects, notify);
+        true
+    }
+
+    /// The quicker the party's actions are compared to the enemies', the
+    /// better the chance.
+    pub fn escape_chance(&self) -> f32 {
+        let party_action_time = average(self.players.iter()
+            .filter(|player| player.combatant.is_standing())
+            .map(|player| {
+                let total: f32 = player.abilities.iter().map(|ability| ability.action_time).sum();
+                total / player.abilities.len() as f32 * player.combatant.statuses.action_time_multiplier()
+            }));
+
+        let enemy_action_time = average(self.enemies.iter()
+            .filter(|enemy| enemy.combatant.is_standing())
+            .map(|enemy| enemy.attack_action_time * enemy.combatant.statuses.action_time_multiplier()));
+
+        (ESCAPE_BASE_CHANCE * enemy_action_time / party_action_time).clamp(ESCAPE_MIN_CHANCE, ESCAPE_MAX_CHANCE)
+    }
+
+    /// Has the pending player try to lead the party away, ending the battle
+    /// on success or losing them time on failure.
+    pub fn player_escape<F: FnMut(BattleEvents)>(&mut self, mut notify: F) {
+        let player_index = match self.pending_player() {
+            Some(i) => i,
+            None => return
+        };
+
+        self.replay.record_command(self.action_time, Command::Escape);
+        self.players_pending.remove(0);
+
+        let chance = self.escape_chance();
+
+        if self.rng.gen::<f32>() < chance {
+            self.end(Outcome::Escaped, &mut notify);
+        } else {
+            self.players[player_index].combatant.schedule(self.action_time, ESCAPE_ACTION_TIME, &mut self.rng);
+            notify(BattleEvents::EscapeFailed(player_index));
+            self.take_queued_actions(&mut notify);
+        }
+    }
+
+    fn targets(&self, caster_index: usize, target: TargetKind) -> Vec<CombatantId> {
+        match target {
+            TargetKind::Enemy => vec![CombatantId::Enemy(self.target_enemy)],
+            TargetKind::AllEnemies => (0..self.enemies.len())
+                .filter(|&i| self.enemies[i].combatant.is_standing())
+                .map(CombatantId::Enemy)
+                .collect(),
+            TargetKind::Caster => vec![CombatantId::Player(caster_index)],
+            TargetKind::DownedAlly => (0..self.players.len())
+                .find(|&i| !self.players[i].combatant.is_standing())
+                .map(CombatantId::Player)
+                .into_iter()
+                .collect()
+        }
+    }
+
+    /// Applies a player's action, made at `balance` and taking `action_time`.
+    fn apply_effects<F: FnMut(BattleEvents)>(
+        &mut self,
+        caster_index: usize,
+        balance: f32,
+        action_time: f32,
+        targets: &[CombatantId],
+        effects: &[AbilityEffect],
+        notify: &mut F
+    ) {
+        let caster_id = CombatantId::Player(caster_index);
+        let on_hit = self.players[caster_index].combatant.on_hit().cloned();
+
+        for effect in effects {
+            for &target in targets {
+                // Earlier effects may have taken the target out
+                if self.combatant(target).is_standing() == effect.needs_downed_target() {
+                    continue;
+                }
+
+                match effect {
+                    AbilityEffect::Damage { power } => {
+                        self.hit(caster_id, target, *power, balance, on_hit.as_ref(), notify);
+                    },
+                    AbilityEffect::Block => {
+                        self.combatant_mut(target).block();
+                        notify(BattleEvents::Blocks(target));
+                    },
+                    AbilityEffect::Status(status) => self.apply_status(target, status, notify),
+                    AbilityEffect::Restore { amount } => self.restore_combatant(target, *amount, notify),
+                    AbilityEffect::RestoreBalance => {
+                        let combatant = self.combatant_mut(target);
+                        combatant.current_balance = combatant.current_balance.max(ON_BALANCE);
+                        notify(BattleEvents::BalanceRestored(target));
+                    },
+                    AbilityEffect::Cure => {
+                        let combatant = self.combatant_mut(target);
+                        let harmful: Vec<StatusKind> = combatant.statuses.iter()
+                            .map(|status| status.kind)
+                            .filter(StatusKind::is_harmful)
+                            .collect();
+
+                        for kind in harmful {
+                            self.combatant_mut(target).statuses.remove(kind);
+                            notify(BattleEvents::StatusExpired(target, kind));
+                        }
+                    },
+                    AbilityEffect::Revive { amount } => {
+                        // Back up with a wait as long as the action that revived them
+                        let now = self.action_time;
+                        let combatant = self.combatant_mut(target);
+                        combatant.next_action_time = now + action_time;
+                        combatant.restore(*amount);
+                        notify(BattleEvents::Revived(target, *amount));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// What a player does with their turn.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub enum PlayerAction {
+    /// Index into the player's abilities
+    Ability(usize),
+    /// Index into the inventory
+    Item(usize)
+}
+
+/// An action chosen before the player's turn came up.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct QueuedAction {
+    pub action: PlayerAction,
+    /// The enemy targeted when it was chosen
+    pub target_enemy: usize
+}
+
+/// How the action clock behaves while players choose what to do.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub enum ClockMode {
+    /// Keeps running, enemies act while players decide
+    Active,
+    /// Stops whenever a player is waiting to act
+    Wait,
+    /// Stops like `Wait` and otherwise jumps straight to the next turn
+    Turns
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+pub enum Outcome {
+    Victory,
+    Defeat,
+    /// The party got away, nobody is rewarded
+    Escaped
 }
 
 pub enum BattleEvents {
-    End(bool),
-    EnemyTakesDamage(usize),
-    EnemyDown(u32),
-    PlayerTakesDamage(usize)
+    End(Outcome),
+    EnemyJoins(usize),
+    EnemyAttacks(usize),
+    /// The enemy has chosen its next move, see `Enemy::intent`
+    EnemyIntent(usize),
+    /// Player index and the hotbar slot of the ability used
+    PlayerUsesAbility(usize, usize),
+    /// Player index and the inventory slot of the item used
+    PlayerUsesItem(usize, usize),
+    EscapeFailed(usize),
+    Blocks(CombatantId),
+    BlocksHit(CombatantId),
+    Damage(DamageReport),
+    /// Fatigue spent on an action, as opposed to damage taken
+    FatigueCost(CombatantId, i32),
+    Recovers(CombatantId, i32),
+    BalanceRestored(CombatantId),
+    Down(CombatantId),
+    /// Back up with the fatigue given
+    Revived(CombatantId, i32),
+    StatusApplied(CombatantId, StatusKind),
+    StatusExpired(CombatantId, StatusKind)
+}
+
+#[derive(Clone, Copy)]
+pub enum DamageSource {
+    Attack(CombatantId),
+    Status(StatusKind)
 }
 
-fn calculate_balance() -> f32 {
-    let damage_group = random::<f32>();
+/// How a single hit's damage was worked out.
+#[derive(Clone)]
+pub struct DamageReport {
+    pub source: DamageSource,
+    pub target: CombatantId,
+    /// Damage before balance and blocking
+    pub raw: i32,
+    /// How much balance scaled the raw damage, 1.0 when on balance
+    pub balance_multiplier: f32,
+    /// Damage taken away by the target blocking
+    pub block_reduction: i32,
+    /// Damage dealt, which may be more than the target had left
+    pub amount: i32
+}
+
+/// Enemies are lettered A to Z in the order they join the battle.
+pub fn enemy_marker(i: usize) -> char {
+    (b'A' + (i % 26) as u8) as char
+}
+
+fn calculate_balance<R: Rng>(rng: &mut R) -> f32 {
+    let damage_group = rng.gen::<f32>();
 
     if damage_group < 0.1 {
-        random::<f32>() * 0.3
+        rng.gen::<f32>() * 0.3
     } else if damage_group > 0.8 {
-        random::<f32>() * 0.7 + 0.3
+        rng.gen::<f32>() * 0.7 + 0.3
     } else {
         0.3
     }
@@ -423,6 +981,12 @@ fn calculate_balance_dmg(base_dmg: i32, balance: f32) -> i32 {
     }
 }
 
+fn average<I: Iterator<Item = f32>>(values: I) -> f32 {
+    let (total, count) = values.fold((0.0, 0), |(total, count), value| (total + value, count + 1));
+
+    if count > 0 { total / count as f32 } else { 0.0 }
+}
+
 fn has_item<T: PartialEq>(list: &Vec<T>, search_item: &T) -> bool {
     for item in list {
         if item == search_item {
@@ -432,90 +996,218 @@ fn has_item<T: PartialEq>(list: &Vec<T>, search_item: &T) -> bool {
     return false;
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::ai::{AiDefinition};
+
+    fn player_definition() -> PlayerDefinition {
+        PlayerDefinition {
+            name: "Player".to_string(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            max_fatigue: 100,
+            first_action: 10.0,
+            block_hit_time_penalty: 50.0,
+            abilities: vec![AbilityDefinition {
+                name: "Attack".to_string(),
+                fatigue_cost: 5,
+                action_time: 100.0,
+                target: TargetKind::Enemy,
+                effects: vec![AbilityEffect::Damage { power: 10 }]
+            }],
+            on_hit: None,
+            block_damage_divisor: combatant::BLOCK_DAMAGE_DIVISOR,
+            balance_recovery: 0.0,
+            equipment: Vec::new()
+        }
+    }
 
-fn draw_enemy_display(
-    ctx: &mut ggez::Context,
-    enemy: &EnemyInBattle,
-    project: &Projector,
-    is_highlighted: bool
-) -> ggez::GameResult {
-    let enemy_hp_guage = resource_guage::create_mesh(ctx, &enemy.hp_guage, &project.local())?;
-    let enemy_balance_guage = balance_guage::create_mesh(ctx, &enemy.balance_guage, &project.local())?;
-    graphics::draw(ctx, &enemy_hp_guage, (project.coords(10.0, 10.0),))?;
-    graphics::draw(ctx, &enemy_balance_guage, (project.coords(10.0, 40.0),))?;
-
-    if is_highlighted {
-        let enemy_highlight = graphics::Mesh::new_rectangle(
-            ctx,
-            graphics::DrawMode::stroke(2.0),
-            graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: project.scale(120.0),
-                h: project.scale(70.0)
-            },
-            palette::GREY
-        )?;
-        graphics::draw(
-            ctx,
-            &enemy_highlight,
-            (project.origin(),)
-        )?;
+    fn enemy_definition() -> EnemyDefinition {
+        EnemyDefinition {
+            name: "Enemy".to_string(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            max_hp: 50,
+            first_action: 1000.0,
+            attack_damage: 10,
+            attack_action_time: 100.0,
+            block_hit_time_penalty: 50.0,
+            experience: 10,
+            drops: Vec::new(),
+            on_hit: None,
+            ai: AiDefinition::default()
+        }
     }
 
-    enemy.action_frame.draw(ctx, &project.local_relative(30.0, 80.0))?;
+    fn battle(players: usize) -> BattleState {
+        let party = vec![player_definition(); players];
+        let fatigue = vec![100; players];
 
-    Ok(())
-}
+        BattleState::new(0, &party, &fatigue, &Inventory::default(), 1, &enemy_definition())
+    }
 
-fn draw_player_display(
-    ctx: &mut ggez::Context,
-    player: &PlayerInBattle,
-    project: &Projector,
-    is_highlighted: bool
-) -> ggez::GameResult {
-
-    let player_fatigue_guage = resource_guage::create_mesh(ctx, &player.fatigue_guage, &project.local())?;
-    let player_balance_guage = balance_guage::create_mesh(ctx, &player.balance_guage, &project.local())?;
-
-    graphics::draw(ctx, &player_fatigue_guage, (project.coords(10.0, 10.0),))?;
-    graphics::draw(ctx, &player_balance_guage, (project.coords(10.0, 40.0),))?;
-
-    if is_highlighted {
-        let player_highlight = graphics::Mesh::new_rectangle(
-            ctx,
-            graphics::DrawMode::stroke(2.0),
-            graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: project.scale(120.0),
-                h: project.scale(70.0)
-            },
-            palette::YELLOW
-        )?;
-        graphics::draw(
-            ctx,
-            &player_highlight,
-            (project.origin(),)
-        )?;
-    }
-
-    if player.stats.is_blocking {
-        let block_icon = graphics::Mesh::new_rectangle(
-            ctx,
-            graphics::DrawMode::fill(),
-            graphics::Rect {
-                x: 0.0,
-                y: 0.0,
-                w: project.scale(10.0),
-                h: project.scale(10.0)
-            },
-            graphics::WHITE
-        )?;
-        graphics::draw(ctx, &block_icon, (project.coords(10.0, 80.0),))?;
+    fn end_count(events: &[BattleEvents]) -> usize {
+        events.iter().filter(|event| matches!(event, BattleEvents::End(_))).count()
+    }
+
+    #[test]
+    fn downing_the_last_enemy_is_a_victory() {
+        let mut battle = battle(1);
+        let mut events = Vec::new();
+
+        battle.damage_combatant(CombatantId::Enemy(0), 50, &mut |event| events.push(event));
+
+        assert_eq!(battle.outcome(), Some(Outcome::Victory));
+        assert_eq!(end_count(&events), 1);
     }
 
-    player.action_frame.draw(ctx, &project.local_relative(30.0, -70.0))?;
+    #[test]
+    fn downing_one_of_two_enemies_retargets_the_other() {
+        let mut battle = battle(1);
+        battle.add_enemy(2, &enemy_definition(), |_| {});
+
+        battle.damage_combatant(CombatantId::Enemy(0), 50, &mut |_| {});
+
+        assert_eq!(battle.outcome(), None);
+        assert_eq!(battle.target_enemy(), 1);
+    }
+
+    #[test]
+    fn downing_the_last_player_is_a_defeat() {
+        let mut battle = battle(2);
+        let mut events = Vec::new();
+
+        battle.damage_combatant(CombatantId::Player(0), 100, &mut |event| events.push(event));
+        assert_eq!(battle.outcome(), None);
+
+        battle.damage_combatant(CombatantId::Player(1), 100, &mut |event| events.push(event));
+        assert_eq!(battle.outcome(), Some(Outcome::Defeat));
+        assert_eq!(end_count(&events), 1);
+    }
+
+    #[test]
+    fn only_the_first_outcome_counts() {
+        let mut battle = battle(1);
+        let mut events = Vec::new();
+
+        battle.damage_combatant(CombatantId::Enemy(0), 50, &mut |event| events.push(event));
+        battle.damage_combatant(CombatantId::Player(0), 100, &mut |event| events.push(event));
+
+        assert_eq!(battle.outcome(), Some(Outcome::Victory));
+        assert_eq!(end_count(&events), 1);
+    }
+
+    #[test]
+    fn status_ticks_stop_once_poison_takes_a_combatant_down() {
+        let mut battle = battle(2);
+        let mut events = Vec::new();
+        let poison = StatusDefinition { kind: StatusKind::Poison, potency: 10.0, duration: 1000.0 };
+        let regen = StatusDefinition { kind: StatusKind::Regen, potency: 5.0, duration: 1000.0 };
+
+        battle.players[0].combatant.current_resource = 15;
+        battle.apply_status(CombatantId::Player(0), &poison, &mut |_| {});
+        battle.apply_status(CombatantId::Player(0), &regen, &mut |_| {});
+
+        // Three ticks of each come due at once
+        battle.action_time = 3.0 * status::STATUS_TICK_INTERVAL;
+        battle.update_statuses(&mut |event| events.push(event));
+
+        let downs = events.iter().filter(|event| matches!(event, BattleEvents::Down(CombatantId::Player(0)))).count();
+        let recovers = events.iter().filter(|event| matches!(event, BattleEvents::Recovers(..))).count();
 
-    Ok(())
+        assert_eq!(downs, 1);
+        assert_eq!(recovers, 0);
+        assert!(!battle.players[0].combatant.is_standing());
+    }
+
+    #[test]
+    fn wait_mode_stops_the_clock_while_a_player_is_pending() {
+        let mut battle = battle(1);
+        battle.set_clock_mode(ClockMode::Wait);
+
+        while !battle.player_move_pending() {
+            battle.tick(1.0 / 60.0, |_| {});
+        }
+
+        let action_time = battle.action_time();
+        battle.tick(1.0, |_| {});
+
+        assert_eq!(battle.action_time(), action_time);
+    }
+
+    #[test]
+    fn active_mode_keeps_the_clock_running_while_a_player_is_pending() {
+        let mut battle = battle(1);
+
+        while !battle.player_move_pending() {
+            battle.tick(1.0 / 60.0, |_| {});
+        }
+
+        let action_time = battle.action_time();
+        battle.tick(1.0, |_| {});
+
+        assert_eq!(battle.action_time(), action_time + ACTION_POINTS_PER_SECOND);
+    }
+
+    #[test]
+    fn turns_mode_jumps_to_just_past_the_next_turn() {
+        let mut battle = battle(1);
+        battle.set_clock_mode(ClockMode::Turns);
+
+        battle.tick(1.0 / 60.0, |_| {});
+
+        assert_eq!(battle.action_time(), 11.0);
+        assert!(battle.player_move_pending());
+    }
+
+    #[test]
+    fn turns_mode_stops_status_ticks_for_anyone_downed_in_the_jump() {
+        let mut battle = battle(2);
+        let mut events = Vec::new();
+        let poison = StatusDefinition { kind: StatusKind::Poison, potency: 10.0, duration: 1000.0 };
+        let regen = StatusDefinition { kind: StatusKind::Regen, potency: 5.0, duration: 1000.0 };
+
+        battle.set_clock_mode(ClockMode::Turns);
+        battle.players[0].combatant.current_resource = 15;
+        battle.apply_status(CombatantId::Player(0), &poison, &mut |_| {});
+        battle.apply_status(CombatantId::Player(0), &regen, &mut |_| {});
+
+        for player in &mut battle.players {
+            player.combatant.next_action_time = 500.0;
+        }
+
+        // Eight ticks of each come due in the one jump
+        battle.tick(1.0 / 60.0, |event| events.push(event));
+
+        let downs = events.iter().filter(|event| matches!(event, BattleEvents::Down(CombatantId::Player(0)))).count();
+        let recovers = events.iter().filter(|event| matches!(event, BattleEvents::Recovers(..))).count();
+
+        assert_eq!(battle.action_time(), 501.0);
+        assert_eq!(downs, 1);
+        assert_eq!(recovers, 0);
+        assert!(!battle.players[0].combatant.is_standing());
+    }
+
+    #[test]
+    fn queued_action_is_taken_behind_a_player_still_deciding() {
+        let mut battle = battle(2);
+        let mut events = Vec::new();
+
+        battle.queue_action(1, PlayerAction::Ability(0));
+
+        // Both players become ready on the same tick, the first is left to choose
+        while !battle.player_move_pending() {
+            battle.tick(1.0 / 60.0, |event| events.push(event));
+        }
+
+        assert!(events.iter().any(|event| matches!(event, BattleEvents::PlayerUsesAbility(1, 0))));
+        assert!(battle.players()[1].queued_action().is_none());
+        assert_eq!(battle.players_pending, vec![0]);
+    }
+
+    #[test]
+    fn balance_scales_damage() {
+        assert_eq!(calculate_balance_dmg(100, ON_BALANCE), 100);
+        assert!(calculate_balance_dmg(100, 0.0) < 100);
+        assert!(calculate_balance_dmg(100, 1.0) > 100);
+    }
 }
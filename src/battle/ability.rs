@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use crate::battle::status::{StatusDefinition};
+
+/// One ability for each hotbar key, 1 through 0.
+pub const MAX_ABILITIES: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetKind {
+    /// The currently targeted enemy
+    Enemy,
+    AllEnemies,
+    #[serde(rename = "self")]
+    Caster,
+    /// The front-most knocked out party member
+    DownedAlly
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AbilityEffect {
+    /// Base damage, scaled by the caster's balance
+    Damage { power: i32 },
+    /// Reduces hits taken until the caster's next action
+    Block,
+    Status(StatusDefinition),
+    /// Fatigue or hp given back
+    Restore { amount: i32 },
+    /// Brings the target's next action up to at least on balance
+    RestoreBalance,
+    /// Removes harmful statuses
+    Cure,
+    /// Gets a knocked out target back up with `amount` fatigue
+    Revive { amount: i32 }
+}
+
+impl AbilityEffect {
+    /// Whether the effect is meant for targets that have been knocked out,
+    /// every other effect only works on those still standing.
+    pub fn needs_downed_target(&self) -> bool {
+        matches!(self, AbilityEffect::Revive { .. })
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbilityDefinition {
+    pub name: String,
+    pub fatigue_cost: i32,
+    pub action_time: f32,
+    pub target: TargetKind,
+    pub effects: Vec<AbilityEffect>
+}
+
+impl AbilityDefinition {
+    pub fn validate(&self, context: &str) -> Result<(), String> {
+        let context = format!("{} ability \"{}\"", context, self.name);
+
+        if self.name.is_empty() {
+            return Err(format!("{} must have a name", context));
+        }
+
+        if self.fatigue_cost < 0 {
+            return Err(format!("{} fatigue_cost must not be negative", context));
+        }
+
+        if self.action_time <= 0.0 {
+            return Err(format!("{} action_time must be greater than 0", context));
+        }
+
+        validate_effects(self.target, &self.effects, &context)
+    }
+
+    pub fn has_damage(&self) -> bool {
+        self.effects.iter().any(|effect| matches!(effect, AbilityEffect::Damage { .. }))
+    }
+
+    pub fn has_block(&self) -> bool {
+        self.effects.iter().any(|effect| matches!(effect, AbilityEffect::Block))
+    }
+}
+
+/// Checks effects make sense on their own and together with the target,
+/// shared by abilities and items.
+pub fn validate_effects(target: TargetKind, effects: &[AbilityEffect], context: &str) -> Result<(), String> {
+    if effects.is_empty() {
+        return Err(format!("{} must have at least one effect", context));
+    }
+
+    for effect in effects {
+        match effect {
+            AbilityEffect::Damage { power } => if *power < 0 {
+                return Err(format!("{} damage power must not be negative", context));
+            },
+            AbilityEffect::Block => if target != TargetKind::Caster {
+                return Err(format!("{} can only block with a target of self", context));
+            },
+            AbilityEffect::Status(status) => status.validate(context)?,
+            AbilityEffect::Restore { amount } => if *amount <= 0 {
+                return Err(format!("{} restore amount must be greater than 0", context));
+            },
+            AbilityEffect::RestoreBalance | AbilityEffect::Cure => {},
+            AbilityEffect::Revive { amount } => if *amount <= 0 {
+                return Err(format!("{} revive amount must be greater than 0", context));
+            }
+        }
+
+        // Knocked out targets can only be revived, and revive needs one
+        if effect.needs_downed_target() != (target == TargetKind::DownedAlly) {
+            return Err(format!("{} must revive, and only revive, a target of downed_ally", context));
+        }
+    }
+
+    Ok(())
+}
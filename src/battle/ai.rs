@@ -0,0 +1,235 @@
+use rand::{Rng};
+use rand::rngs::{StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::battle::{Enemy, Player};
+
+/// Which behaviour an enemy archetype uses, see `create`.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AiDefinition {
+    #[default]
+    Aggressive,
+    LowestFatigue,
+    Defensive,
+    Random { attack_weight: f32, block_weight: f32 }
+}
+
+impl AiDefinition {
+    pub fn validate(&self, context: &str) -> Result<(), String> {
+        match self {
+            AiDefinition::Random { attack_weight, block_weight } => {
+                if *attack_weight < 0.0 || *block_weight < 0.0 || attack_weight + block_weight <= 0.0 {
+                    Err(format!("{} ai weights must not be negative and must not both be 0", context))
+                } else {
+                    Ok(())
+                }
+            },
+            _ => Ok(())
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnemyMove {
+    /// Index of the player to attack
+    Attack(usize),
+    Block
+}
+
+/// Decides what an enemy does on its next turn, asked as soon as that turn is
+/// scheduled. Only ever asked while at least one player is still standing.
+pub trait EnemyAi {
+    fn choose_move(&self, enemy: &Enemy, players: &[Player], rng: &mut StdRng) -> EnemyMove;
+}
+
+pub fn create(definition: &AiDefinition) -> Box<dyn EnemyAi> {
+    match definition {
+        AiDefinition::Aggressive => Box::new(Aggressive),
+        AiDefinition::LowestFatigue => Box::new(LowestFatigue),
+        AiDefinition::Defensive => Box::new(Defensive),
+        AiDefinition::Random { attack_weight, block_weight } => Box::new(RandomWeighted {
+            attack_weight: *attack_weight,
+            block_weight: *block_weight
+        })
+    }
+}
+
+/// Always attacks the front of the formation.
+pub struct Aggressive;
+
+impl EnemyAi for Aggressive {
+    fn choose_move(&self, _enemy: &Enemy, players: &[Player], _rng: &mut StdRng) -> EnemyMove {
+        EnemyMove::Attack(front_of_formation(players))
+    }
+}
+
+/// Picks off whoever is closest to going down.
+pub struct LowestFatigue;
+
+impl EnemyAi for LowestFatigue {
+    fn choose_move(&self, _enemy: &Enemy, players: &[Player], _rng: &mut StdRng) -> EnemyMove {
+        let target = (0..players.len())
+            .filter(|&i| players[i].combatant.is_standing())
+            .min_by_key(|&i| players[i].combatant.current_resource)
+            .unwrap_or(0);
+
+        EnemyMove::Attack(target)
+    }
+}
+
+/// Attacks like `Aggressive` but blocks instead of swinging off-balance.
+pub struct Defensive;
+
+impl EnemyAi for Defensive {
+    fn choose_move(&self, enemy: &Enemy, players: &[Player], _rng: &mut StdRng) -> EnemyMove {
+        if enemy.combatant.current_balance < 0.3 {
+            EnemyMove::Block
+        } else {
+            EnemyMove::Attack(front_of_formation(players))
+        }
+    }
+}
+
+/// Rolls between attacking a random player and blocking.
+pub struct RandomWeighted {
+    attack_weight: f32,
+    block_weight: f32
+}
+
+impl EnemyAi for RandomWeighted {
+    fn choose_move(&self, _enemy: &Enemy, players: &[Player], rng: &mut StdRng) -> EnemyMove {
+        let roll = rng.gen::<f32>() * (self.attack_weight + self.block_weight);
+
+        if roll >= self.attack_weight {
+            return EnemyMove::Block;
+        }
+
+        let standing: Vec<usize> = (0..players.len())
+            .filter(|&i| players[i].combatant.is_standing())
+            .collect();
+
+        EnemyMove::Attack(standing[rng.gen_range(0, standing.len())])
+    }
+}
+
+/// Players are in formation order, so the front is the first still standing.
+/// This is who enemies go for unless their behaviour says otherwise.
+fn front_of_formation(players: &[Player]) -> usize {
+    players.iter()
+        .position(|player| player.combatant.is_standing())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng};
+
+    use super::*;
+    use crate::battle::ability::{AbilityDefinition, AbilityEffect, TargetKind};
+    use crate::battle::combatant::{BLOCK_DAMAGE_DIVISOR};
+    use crate::definitions::{EnemyDefinition, PlayerDefinition};
+
+    fn players(fatigue: &[i32]) -> Vec<Player> {
+        let definition = PlayerDefinition {
+            name: "Player".to_string(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            max_fatigue: 100,
+            first_action: 10.0,
+            block_hit_time_penalty: 50.0,
+            abilities: vec![AbilityDefinition {
+                name: "Attack".to_string(),
+                fatigue_cost: 5,
+                action_time: 100.0,
+                target: TargetKind::Enemy,
+                effects: vec![AbilityEffect::Damage { power: 10 }]
+            }],
+            on_hit: None,
+            block_damage_divisor: BLOCK_DAMAGE_DIVISOR,
+            balance_recovery: 0.0,
+            equipment: Vec::new()
+        };
+
+        let mut rng = StdRng::seed_from_u64(0);
+
+        fatigue.iter().map(|&fatigue| Player::new(&definition, fatigue, &mut rng)).collect()
+    }
+
+    fn enemy(balance: f32) -> Enemy {
+        let definition = EnemyDefinition {
+            name: "Enemy".to_string(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            max_hp: 50,
+            first_action: 100.0,
+            attack_damage: 10,
+            attack_action_time: 100.0,
+            block_hit_time_penalty: 50.0,
+            experience: 10,
+            drops: Vec::new(),
+            on_hit: None,
+            ai: AiDefinition::default()
+        };
+
+        let mut enemy = Enemy::new(1, 'A', &definition, &mut StdRng::seed_from_u64(0));
+        enemy.combatant.current_balance = balance;
+        enemy
+    }
+
+    fn choose(definition: AiDefinition, enemy: &Enemy, players: &[Player]) -> EnemyMove {
+        create(&definition).choose_move(enemy, players, &mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn aggressive_attacks_the_front_of_the_formation() {
+        assert_eq!(choose(AiDefinition::Aggressive, &enemy(0.3), &players(&[50, 10])), EnemyMove::Attack(0));
+        assert_eq!(choose(AiDefinition::Aggressive, &enemy(0.3), &players(&[0, 90, 10])), EnemyMove::Attack(1));
+    }
+
+    #[test]
+    fn lowest_fatigue_attacks_whoever_is_closest_to_going_down() {
+        assert_eq!(choose(AiDefinition::LowestFatigue, &enemy(0.3), &players(&[80, 30, 0, 60])), EnemyMove::Attack(1));
+    }
+
+    #[test]
+    fn defensive_blocks_when_off_balance() {
+        let players = players(&[0, 90]);
+
+        assert_eq!(choose(AiDefinition::Defensive, &enemy(0.1), &players), EnemyMove::Block);
+        assert_eq!(choose(AiDefinition::Defensive, &enemy(0.3), &players), EnemyMove::Attack(1));
+    }
+
+    #[test]
+    fn random_only_attacks_standing_players() {
+        let ai = create(&AiDefinition::Random { attack_weight: 1.0, block_weight: 0.0 });
+        let players = players(&[0, 90, 0, 40]);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let choice = ai.choose_move(&enemy(0.3), &players, &mut rng);
+            assert!(choice == EnemyMove::Attack(1) || choice == EnemyMove::Attack(3));
+        }
+    }
+
+    #[test]
+    fn random_always_blocks_without_an_attack_weight() {
+        let ai = create(&AiDefinition::Random { attack_weight: 0.0, block_weight: 1.0 });
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            assert_eq!(ai.choose_move(&enemy(0.3), &players(&[90]), &mut rng), EnemyMove::Block);
+        }
+    }
+
+    #[test]
+    fn random_follows_its_weights() {
+        let ai = create(&AiDefinition::Random { attack_weight: 3.0, block_weight: 1.0 });
+        let players = players(&[90, 90]);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let blocks = (0..1000)
+            .filter(|_| ai.choose_move(&enemy(0.3), &players, &mut rng) == EnemyMove::Block)
+            .count();
+
+        assert!(blocks > 200 && blocks < 300, "{} blocks in 1000", blocks);
+    }
+}
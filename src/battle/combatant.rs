@@ -0,0 +1,200 @@
+use rand::{Rng};
+use std::cmp;
+
+use crate::battle::calculate_balance;
+use crate::battle::status::{StatusDefinition, StatusEffects};
+
+/// Hits taken while blocking are divided by this unless equipment says otherwise.
+pub const BLOCK_DAMAGE_DIVISOR: i32 = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CombatantId {
+    Player(usize),
+    Enemy(usize)
+}
+
+/// State and rules shared by both sides of a battle. The resource is fatigue
+/// for players and hp for enemies, either way reaching 0 takes them out.
+pub struct Combatant {
+    /// Enemies are named after their archetype followed by their marker, e.g. "Beetle B"
+    pub name: String,
+    pub color: [f32; 4],
+    pub max_resource: i32,
+    pub current_resource: i32,
+    pub current_balance: f32,
+    pub next_action_time: f32,
+    pub is_blocking: bool,
+    pub statuses: StatusEffects,
+    block_end_time: f32,
+    block_hit_time_penalty: f32,
+    block_damage_divisor: i32,
+    balance_recovery: f32,
+    on_hit: Option<StatusDefinition>
+}
+
+impl Combatant {
+    pub(super) fn new<R: Rng>(
+        name: String,
+        color: [f32; 4],
+        max_resource: i32,
+        first_action: f32,
+        block_hit_time_penalty: f32,
+        on_hit: Option<StatusDefinition>,
+        rng: &mut R
+    ) -> Self {
+        Self {
+            name: name,
+            color: color,
+            max_resource: max_resource,
+            current_resource: max_resource,
+            current_balance: calculate_balance(rng),
+            next_action_time: first_action,
+            is_blocking: false,
+            statuses: StatusEffects::default(),
+            block_end_time: 0.0,
+            block_hit_time_penalty: block_hit_time_penalty,
+            block_damage_divisor: BLOCK_DAMAGE_DIVISOR,
+            balance_recovery: 0.0,
+            on_hit: on_hit
+        }
+    }
+
+    /// Sets how well this combatant blocks, see `receive_hit`.
+    pub(super) fn set_block_damage_divisor(&mut self, block_damage_divisor: i32) {
+        self.block_damage_divisor = block_damage_divisor;
+    }
+
+    /// Sets how much is added to every balance rolled after this combatant's first action.
+    pub(super) fn set_balance_recovery(&mut self, balance_recovery: f32) {
+        self.balance_recovery = balance_recovery;
+    }
+
+    pub fn is_standing(&self) -> bool {
+        self.current_resource > 0
+    }
+
+    /// Action time lost when a hit lands on this combatant's block.
+    pub fn block_hit_time_penalty(&self) -> f32 {
+        self.block_hit_time_penalty
+    }
+
+    /// Status landed on whoever this combatant damages.
+    pub fn on_hit(&self) -> Option<&StatusDefinition> {
+        self.on_hit.as_ref()
+    }
+
+    /// Sets up the next action after one taking `action_time`, scaled by
+    /// haste and slow, and rolls the balance it will be made with.
+    pub(super) fn schedule<R: Rng>(&mut self, now: f32, action_time: f32, rng: &mut R) {
+        self.next_action_time = now + action_time * self.statuses.action_time_multiplier();
+        self.current_balance = (calculate_balance(rng) + self.balance_recovery).min(1.0);
+    }
+
+    /// Blocks until the next action comes up.
+    pub(super) fn block(&mut self) {
+        self.is_blocking = true;
+        self.block_end_time = self.next_action_time;
+    }
+
+    pub(super) fn end_expired_block(&mut self, now: f32) {
+        if self.is_blocking && now > self.block_end_time {
+            self.is_blocking = false;
+        }
+    }
+
+    /// Reduces a hit if blocking, which also knocks the next action back.
+    /// Returns the damage left and whether it was blocked.
+    pub(super) fn receive_hit(&mut self, dmg: i32) -> (i32, bool) {
+        if self.is_blocking {
+            self.next_action_time += self.block_hit_time_penalty;
+            (dmg / self.block_damage_divisor, true)
+        } else {
+            (dmg, false)
+        }
+    }
+
+    pub(super) fn take_damage(&mut self, amount: i32) {
+        self.current_resource = cmp::max(0, self.current_resource - amount);
+
+        if self.current_resource == 0 {
+            self.statuses = StatusEffects::default();
+        }
+    }
+
+    pub(super) fn restore(&mut self, amount: i32) {
+        self.current_resource = cmp::min(self.max_resource, self.current_resource + amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng};
+    use rand::rngs::{StdRng};
+
+    use super::*;
+    use crate::battle::status::{StatusKind};
+
+    fn combatant() -> Combatant {
+        Combatant::new(
+            "Test".to_string(),
+            [1.0, 1.0, 1.0, 1.0],
+            100,
+            50.0,
+            80.0,
+            None,
+            &mut StdRng::seed_from_u64(0)
+        )
+    }
+
+    #[test]
+    fn unblocked_hit_is_taken_in_full() {
+        let mut combatant = combatant();
+
+        assert_eq!(combatant.receive_hit(20), (20, false));
+        assert_eq!(combatant.next_action_time, 50.0);
+    }
+
+    #[test]
+    fn blocked_hit_is_divided_and_knocks_back_the_next_action() {
+        let mut combatant = combatant();
+        combatant.block();
+
+        assert_eq!(combatant.receive_hit(20), (20 / BLOCK_DAMAGE_DIVISOR, true));
+        assert_eq!(combatant.next_action_time, 50.0 + 80.0);
+    }
+
+    #[test]
+    fn blocked_hit_uses_the_combatants_own_divisor() {
+        let mut combatant = combatant();
+        combatant.set_block_damage_divisor(5);
+        combatant.block();
+
+        assert_eq!(combatant.receive_hit(20), (4, true));
+    }
+
+    #[test]
+    fn block_ends_once_the_next_action_has_passed() {
+        let mut combatant = combatant();
+        combatant.block();
+
+        combatant.end_expired_block(50.0);
+        assert!(combatant.is_blocking);
+
+        combatant.end_expired_block(51.0);
+        assert!(!combatant.is_blocking);
+    }
+
+    #[test]
+    fn take_damage_stops_at_zero_and_clears_statuses() {
+        let mut combatant = combatant();
+        combatant.statuses.apply(&StatusDefinition { kind: StatusKind::Poison, potency: 2.0, duration: 100.0 }, 0.0);
+
+        combatant.take_damage(60);
+        assert!(combatant.statuses.has(StatusKind::Poison));
+
+        combatant.take_damage(60);
+        assert_eq!(combatant.current_resource, 0);
+        assert!(!combatant.is_standing());
+        assert_eq!(combatant.statuses.iter().count(), 0);
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use crate::battle::ability::{self, AbilityEffect, TargetKind};
+
+/// A consumable used in place of an ability. Using one costs no fatigue.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDefinition {
+    pub name: String,
+    pub action_time: f32,
+    pub target: TargetKind,
+    pub effects: Vec<AbilityEffect>
+}
+
+impl ItemDefinition {
+    pub fn validate(&self) -> Result<(), String> {
+        let context = format!("item \"{}\"", self.name);
+
+        if self.name.is_empty() {
+            return Err(format!("{} must have a name", context));
+        }
+
+        if self.action_time <= 0.0 {
+            return Err(format!("{} action_time must be greater than 0", context));
+        }
+
+        ability::validate_effects(self.target, &self.effects, &context)
+    }
+}
+
+/// A number of one item, referred to by name in the definitions.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemCount {
+    pub item: String,
+    pub count: u32
+}
+
+/// An item an enemy may leave behind when defeated.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDrop {
+    pub item: String,
+    /// Between 0.0 and 1.0
+    pub chance: f32
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct InventorySlot {
+    pub item: ItemDefinition,
+    pub count: u32
+}
+
+/// Items shared by the whole party. Slots stay put when they run out so
+/// indexes held by a battle view keep pointing at the same item.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Inventory {
+    slots: Vec<InventorySlot>
+}
+
+impl Inventory {
+    pub fn add(&mut self, item: &ItemDefinition, count: u32) {
+        match self.slots.iter_mut().find(|slot| slot.item.name == item.name) {
+            Some(slot) => slot.count += count,
+            None => self.slots.push(InventorySlot {
+                item: item.clone(),
+                count: count
+            })
+        }
+    }
+
+    pub fn slots(&self) -> &[InventorySlot] {
+        &self.slots
+    }
+
+    pub fn has(&self, index: usize) -> bool {
+        self.slots.get(index).map_or(false, |slot| slot.count > 0)
+    }
+
+    pub(super) fn take(&mut self, index: usize) -> ItemDefinition {
+        let slot = &mut self.slots[index];
+        slot.count -= 1;
+        slot.item.clone()
+    }
+
+    /// Clears out used up slots, only safe to do outside of battle.
+    pub fn remove_empty(&mut self) {
+        self.slots.retain(|slot| slot.count > 0);
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path};
+
+use crate::battle::{BattleState, BattleEvents, ClockMode, Outcome, PlayerAction};
+use crate::battle::item::{Inventory};
+use crate::definitions::{EnemyDefinition, PlayerDefinition};
+
+/// Everything needed to rebuild a battle: the starting state, the seed
+/// and every input in the order it was applied.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub party: Vec<PlayerDefinition>,
+    pub party_fatigue: Vec<i32>,
+    pub inventory: Inventory,
+    pub enemy_id: u32,
+    pub enemy: EnemyDefinition,
+    pub entries: Vec<ReplayEntry>,
+    pub outcome: Option<Outcome>
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub enum ReplayEntry {
+    Ticks { delta: f32, count: u32 },
+    Command { action_time: f32, command: Command }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub enum Command {
+    /// Hotbar slot of the pending player's ability
+    UseAbility(usize),
+    /// Inventory slot of the item the pending player uses
+    UseItem(usize),
+    Escape,
+    QueueAction(usize, PlayerAction),
+    CancelQueuedAction(usize),
+    Target(usize),
+    ClockMode(ClockMode),
+    AddEnemy(u32, EnemyDefinition)
+}
+
+impl Replay {
+    pub fn new(
+        seed: u64,
+        party: &[PlayerDefinition],
+        party_fatigue: &[i32],
+        inventory: &Inventory,
+        enemy_id: u32,
+        enemy: &EnemyDefinition
+    ) -> Self {
+        Self {
+            seed: seed,
+            party: party.to_vec(),
+            party_fatigue: party_fatigue.to_vec(),
+            inventory: inventory.clone(),
+            enemy_id: enemy_id,
+            enemy: enemy.clone(),
+            entries: Vec::new(),
+            outcome: None
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let replay = serde_json::from_reader(BufReader::new(file))?;
+        Ok(replay)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn start(&self) -> BattleState {
+        BattleState::new(self.seed, &self.party, &self.party_fatigue, &self.inventory, self.enemy_id, &self.enemy)
+    }
+
+    pub(crate) fn record_tick(&mut self, delta: f32) {
+        // Runs of identical ticks are stored as a count to keep files small
+        if let Some(ReplayEntry::Ticks { delta: last_delta, count }) = self.entries.last_mut() {
+            if *last_delta == delta {
+                *count += 1;
+                return;
+            }
+        }
+
+        self.entries.push(ReplayEntry::Ticks { delta: delta, count: 1 });
+    }
+
+    pub(crate) fn record_command(&mut self, action_time: f32, command: Command) {
+        self.entries.push(ReplayEntry::Command {
+            action_time: action_time,
+            command: command
+        });
+    }
+}
+
+/// Feeds a replay's entries back into a battle one tick at a time.
+pub struct ReplayPlayer {
+    replay: Replay,
+    entry: usize,
+    ticks: u32,
+    desynced: bool
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay: replay,
+            entry: 0,
+            ticks: 0,
+            desynced: false
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// True if a command was reached at a different action time than it was
+    /// recorded at, meaning the battle rules have changed since recording.
+    pub fn desynced(&self) -> bool {
+        self.desynced
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.entry >= self.replay.entries.len()
+    }
+
+    /// Applies any commands due before the next tick and then runs that tick.
+    pub fn step<F: FnMut(BattleEvents)>(&mut self, battle: &mut BattleState, mut notify: F) {
+
+        while let Some(entry) = self.replay.entries.get(self.entry) {
+            match entry {
+                ReplayEntry::Command { action_time, command } => {
+                    if *action_time != battle.action_time() {
+                        self.desynced = true;
+                    }
+
+                    battle.apply_command(command, &mut notify);
+                    self.entry += 1;
+                },
+                ReplayEntry::Ticks { delta, count } => {
+                    battle.tick(*delta, &mut notify);
+                    self.ticks += 1;
+
+                    if self.ticks == *count {
+                        self.ticks = 0;
+                        self.entry += 1;
+                    }
+
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Runs the whole replay without presenting it.
+    pub fn play_to_end(&mut self) -> BattleState {
+        let mut battle = self.replay.start();
+
+        while !self.is_finished() {
+            self.step(&mut battle, |_| {});
+        }
+
+        battle
+    }
+}
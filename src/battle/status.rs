@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+/// Poison and regeneration take effect every this much action time.
+pub const STATUS_TICK_INTERVAL: f32 = 60.0;
+
+/// What `potency` means depends on the kind:
+/// - Poison, Regen: fatigue or hp lost/restored every tick
+/// - Stun: action time the next action is pushed back by
+/// - Haste, Slow: multiplier applied to action times
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusKind {
+    Poison,
+    Stun,
+    Haste,
+    Slow,
+    Regen
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "Poison",
+            StatusKind::Stun => "Stun",
+            StatusKind::Haste => "Haste",
+            StatusKind::Slow => "Slow",
+            StatusKind::Regen => "Regen"
+        }
+    }
+
+    /// Whether a cure takes it away.
+    pub fn is_harmful(&self) -> bool {
+        match self {
+            StatusKind::Poison | StatusKind::Stun | StatusKind::Slow => true,
+            StatusKind::Haste | StatusKind::Regen => false
+        }
+    }
+
+    fn opposite(&self) -> Option<StatusKind> {
+        match self {
+            StatusKind::Haste => Some(StatusKind::Slow),
+            StatusKind::Slow => Some(StatusKind::Haste),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusDefinition {
+    pub kind: StatusKind,
+    pub potency: f32,
+    pub duration: f32
+}
+
+impl StatusDefinition {
+    pub fn validate(&self, context: &str) -> Result<(), String> {
+        if self.duration <= 0.0 {
+            return Err(format!("{} {} duration must be greater than 0", context, self.kind.name()));
+        }
+
+        let valid_potency = match self.kind {
+            StatusKind::Haste => self.potency > 0.0 && self.potency < 1.0,
+            StatusKind::Slow => self.potency > 1.0,
+            _ => self.potency > 0.0
+        };
+
+        if valid_potency {
+            Ok(())
+        } else {
+            Err(format!("{} {} potency {} is out of range", context, self.kind.name(), self.potency))
+        }
+    }
+}
+
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub potency: f32,
+    pub end_time: f32,
+    next_tick_time: f32
+}
+
+pub enum StatusChange {
+    Applied,
+    /// The new status and an opposing one cancelled each other out
+    Cancelled(StatusKind),
+    Ignored
+}
+
+#[derive(Default)]
+pub struct StatusEffects {
+    effects: Vec<StatusEffect>
+}
+
+impl StatusEffects {
+
+    /// Stacking rules: poison and regen add their potency together, haste and
+    /// slow keep the strongest, stun can't be reapplied until it wears off.
+    /// Reapplying always extends the duration to whichever ends later.
+    pub fn apply(&mut self, definition: &StatusDefinition, action_time: f32) -> StatusChange {
+
+        if let Some(opposite) = definition.kind.opposite() {
+            if self.has(opposite) {
+                self.remove(opposite);
+                return StatusChange::Cancelled(opposite);
+            }
+        }
+
+        let end_time = action_time + definition.duration;
+
+        match self.effects.iter_mut().find(|effect| effect.kind == definition.kind) {
+            Some(effect) => {
+                match effect.kind {
+                    StatusKind::Stun => return StatusChange::Ignored,
+                    StatusKind::Poison | StatusKind::Regen => effect.potency += definition.potency,
+                    StatusKind::Haste => effect.potency = effect.potency.min(definition.potency),
+                    StatusKind::Slow => effect.potency = effect.potency.max(definition.potency)
+                }
+
+                effect.end_time = effect.end_time.max(end_time);
+            },
+            None => {
+                self.effects.push(StatusEffect {
+                    kind: definition.kind,
+                    potency: definition.potency,
+                    end_time: end_time,
+                    next_tick_time: action_time + STATUS_TICK_INTERVAL
+                });
+            }
+        }
+
+        StatusChange::Applied
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    pub fn remove(&mut self, kind: StatusKind) {
+        self.effects.retain(|effect| effect.kind != kind);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.effects.iter()
+    }
+
+    /// Scale to apply to the action time of anything scheduled now.
+    pub fn action_time_multiplier(&self) -> f32 {
+        self.effects.iter()
+            .filter(|effect| effect.kind == StatusKind::Haste || effect.kind == StatusKind::Slow)
+            .fold(1.0, |multiplier, effect| multiplier * effect.potency)
+    }
+
+    /// Collects poison and regen ticks that have come due by `action_time`.
+    pub fn take_ticks(&mut self, action_time: f32) -> Vec<(StatusKind, i32)> {
+        let mut ticks = Vec::new();
+
+        for effect in &mut self.effects {
+            if effect.kind != StatusKind::Poison && effect.kind != StatusKind::Regen {
+                continue;
+            }
+
+            while effect.next_tick_time <= action_time && effect.next_tick_time <= effect.end_time {
+                ticks.push((effect.kind, effect.potency.round() as i32));
+                effect.next_tick_time += STATUS_TICK_INTERVAL;
+            }
+        }
+
+        ticks
+    }
+
+    pub fn take_expired(&mut self, action_time: f32) -> Vec<StatusKind> {
+        let expired = self.effects.iter()
+            .filter(|effect| effect.end_time < action_time)
+            .map(|effect| effect.kind)
+            .collect();
+
+        self.effects.retain(|effect| effect.end_time >= action_time);
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(kind: StatusKind, potency: f32, duration: f32) -> StatusDefinition {
+        StatusDefinition { kind: kind, potency: potency, duration: duration }
+    }
+
+    fn potency(statuses: &StatusEffects, kind: StatusKind) -> f32 {
+        statuses.iter().find(|effect| effect.kind == kind).unwrap().potency
+    }
+
+    #[test]
+    fn poison_stacks_potency_and_keeps_the_later_end() {
+        let mut statuses = StatusEffects::default();
+
+        statuses.apply(&status(StatusKind::Poison, 2.0, 300.0), 0.0);
+        statuses.apply(&status(StatusKind::Poison, 3.0, 100.0), 100.0);
+
+        assert_eq!(potency(&statuses, StatusKind::Poison), 5.0);
+        assert_eq!(statuses.iter().next().unwrap().end_time, 300.0);
+
+        statuses.apply(&status(StatusKind::Poison, 1.0, 300.0), 100.0);
+        assert_eq!(statuses.iter().next().unwrap().end_time, 400.0);
+    }
+
+    #[test]
+    fn haste_and_slow_keep_the_strongest() {
+        let mut statuses = StatusEffects::default();
+
+        statuses.apply(&status(StatusKind::Haste, 0.5, 100.0), 0.0);
+        statuses.apply(&status(StatusKind::Haste, 0.8, 100.0), 0.0);
+        assert_eq!(potency(&statuses, StatusKind::Haste), 0.5);
+
+        let mut statuses = StatusEffects::default();
+
+        statuses.apply(&status(StatusKind::Slow, 2.0, 100.0), 0.0);
+        statuses.apply(&status(StatusKind::Slow, 1.5, 100.0), 0.0);
+        assert_eq!(potency(&statuses, StatusKind::Slow), 2.0);
+        assert_eq!(statuses.action_time_multiplier(), 2.0);
+    }
+
+    #[test]
+    fn haste_and_slow_cancel_each_other_out() {
+        let mut statuses = StatusEffects::default();
+
+        statuses.apply(&status(StatusKind::Haste, 0.5, 100.0), 0.0);
+
+        assert!(matches!(
+            statuses.apply(&status(StatusKind::Slow, 2.0, 100.0), 0.0),
+            StatusChange::Cancelled(StatusKind::Haste)
+        ));
+        assert_eq!(statuses.iter().count(), 0);
+        assert_eq!(statuses.action_time_multiplier(), 1.0);
+    }
+
+    #[test]
+    fn stun_is_ignored_while_already_stunned() {
+        let mut statuses = StatusEffects::default();
+
+        assert!(matches!(statuses.apply(&status(StatusKind::Stun, 50.0, 100.0), 0.0), StatusChange::Applied));
+        assert!(matches!(statuses.apply(&status(StatusKind::Stun, 50.0, 300.0), 0.0), StatusChange::Ignored));
+        assert_eq!(statuses.iter().next().unwrap().end_time, 100.0);
+    }
+
+    #[test]
+    fn ticks_come_due_every_interval_until_the_end() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(&status(StatusKind::Regen, 4.0, 2.5 * STATUS_TICK_INTERVAL), 0.0);
+
+        assert!(statuses.take_ticks(STATUS_TICK_INTERVAL - 1.0).is_empty());
+        assert_eq!(statuses.take_ticks(10.0 * STATUS_TICK_INTERVAL), vec![(StatusKind::Regen, 4), (StatusKind::Regen, 4)]);
+    }
+
+    #[test]
+    fn expired_statuses_are_taken_once_their_end_has_passed() {
+        let mut statuses = StatusEffects::default();
+        statuses.apply(&status(StatusKind::Poison, 1.0, 100.0), 0.0);
+
+        assert!(statuses.take_expired(100.0).is_empty());
+        assert_eq!(statuses.take_expired(101.0), vec![StatusKind::Poison]);
+        assert_eq!(statuses.iter().count(), 0);
+    }
+}
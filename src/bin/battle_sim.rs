@@ -0,0 +1,329 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::{StdRng};
+use std::env;
+use std::process;
+
+use rpg_battle::battle::{self, BattleState, BattleEvents, DamageSource, Outcome};
+use rpg_battle::battle::combatant::{CombatantId};
+use rpg_battle::battle::item::{Inventory};
+use rpg_battle::battle::replay::{Replay, ReplayPlayer};
+use rpg_battle::definitions::{self, Definitions, PlayerDefinition};
+use rpg_battle::party::{PartyMember};
+
+const DELTA: f32 = 1.0 / 60.0;
+const MAX_ACTION_TIME: f32 = 100000.0;
+
+const USAGE: &str = "Usage: battle_sim [--runs N] [--seed N] [--policy attack|guard|random] [--enemy NAME] [--enemies N]
+       battle_sim --replay FILE [--replay FILE ...]";
+
+#[derive(Clone, Copy)]
+enum Policy {
+    Attack,
+    Guard,
+    Random
+}
+
+struct Options {
+    runs: u32,
+    seed: u64,
+    policy: Policy,
+    enemy: Option<String>,
+    enemy_count: u32,
+    replays: Vec<String>
+}
+
+struct BattleResult {
+    outcome: Option<Outcome>,
+    duration: f32,
+    player_damage: Vec<i32>,
+    enemy_damage: Vec<i32>,
+    player_fatigue: Vec<i32>
+}
+
+struct Totals {
+    victories: u32,
+    defeats: u32,
+    escapes: u32,
+    unfinished: u32,
+    duration: f32,
+    player_damage: Vec<f32>,
+    enemy_damage: Vec<f32>,
+    player_fatigue: Vec<f32>
+}
+
+impl Totals {
+    fn new(players: usize, enemies: usize) -> Self {
+        Self {
+            victories: 0,
+            defeats: 0,
+            escapes: 0,
+            unfinished: 0,
+            duration: 0.0,
+            player_damage: vec![0.0; players],
+            enemy_damage: vec![0.0; enemies],
+            player_fatigue: vec![0.0; players]
+        }
+    }
+
+    fn add(&mut self, result: &BattleResult) {
+        match result.outcome {
+            Some(Outcome::Victory) => self.victories += 1,
+            Some(Outcome::Defeat) => self.defeats += 1,
+            Some(Outcome::Escaped) => self.escapes += 1,
+            None => self.unfinished += 1
+        }
+
+        self.duration += result.duration;
+
+        add_all(&mut self.player_damage, &result.player_damage);
+        add_all(&mut self.enemy_damage, &result.enemy_damage);
+        add_all(&mut self.player_fatigue, &result.player_fatigue);
+    }
+}
+
+fn add_all(totals: &mut Vec<f32>, values: &[i32]) {
+    for (total, value) in totals.iter_mut().zip(values) {
+        *total += *value as f32;
+    }
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        runs: 1000,
+        seed: 0,
+        policy: Policy::Guard,
+        enemy: None,
+        enemy_count: 1,
+        replays: Vec::new()
+    };
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} requires a value", arg));
+
+        match arg.as_str() {
+            "--runs" => options.runs = parse_number(&value()?)?,
+            "--seed" => options.seed = parse_number(&value()?)?,
+            "--enemies" => options.enemy_count = parse_number(&value()?)?,
+            "--enemy" => options.enemy = Some(value()?),
+            "--replay" => options.replays.push(value()?),
+            "--policy" => {
+                options.policy = match value()?.as_str() {
+                    "attack" => Policy::Attack,
+                    "guard" => Policy::Guard,
+                    "random" => Policy::Random,
+                    other => return Err(format!("Unknown policy {}", other))
+                };
+            },
+            "--help" => return Err(USAGE.to_string()),
+            other => return Err(format!("Unknown argument {}\n{}", other, USAGE))
+        }
+    }
+
+    if options.runs == 0 || options.enemy_count == 0 {
+        return Err("--runs and --enemies must be at least 1".to_string());
+    }
+
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} is not a valid number", value))
+}
+
+/// Picks a hotbar slot. Attack and guard use the player's first damaging
+/// and first blocking abilities, falling back to the first slot.
+fn choose_ability<R: Rng>(policy: Policy, battle: &BattleState, player: usize, rng: &mut R) -> usize {
+    let abilities = battle.players()[player].abilities();
+    let attack = abilities.iter().position(|ability| ability.has_damage()).unwrap_or(0);
+    let block = abilities.iter().position(|ability| ability.has_block()).unwrap_or(attack);
+
+    match policy {
+        Policy::Attack => attack,
+        // Blocking off-balance turns wastes the least damage
+        Policy::Guard => if battle.players()[player].combatant.current_balance < 0.3 { block } else { attack },
+        Policy::Random => rng.gen_range(0, abilities.len())
+    }
+}
+
+fn total_fatigue(battle: &BattleState) -> Vec<i32> {
+    battle.players().iter().map(|player| player.combatant.current_resource).collect()
+}
+
+/// A fresh level 1 party in its starting equipment.
+fn starting_party(definitions: &Definitions) -> Vec<PlayerDefinition> {
+    definitions.party.iter()
+        .map(|definition| PartyMember::new(definition, definitions).battle_definition(&definitions.levels))
+        .collect()
+}
+
+fn run_battle(definitions: &Definitions, enemy: usize, options: &Options, seed: u64) -> BattleResult {
+    let mut policy_rng = StdRng::seed_from_u64(seed);
+    let enemy_definition = &definitions.enemies[enemy];
+    let party = starting_party(definitions);
+    let fatigue: Vec<i32> = party.iter().map(|player| player.max_fatigue).collect();
+
+    // Policies only choose abilities, so the party goes without items
+    let mut battle = BattleState::new(seed, &party, &fatigue, &Inventory::default(), 1, enemy_definition);
+
+    for id in 1..options.enemy_count {
+        battle.add_enemy(id + 1, enemy_definition, |_| {});
+    }
+
+    let mut result = BattleResult {
+        outcome: None,
+        duration: 0.0,
+        player_damage: vec![0; definitions.party.len()],
+        enemy_damage: vec![0; options.enemy_count as usize],
+        player_fatigue: Vec::new()
+    };
+
+    let mut events = Vec::new();
+
+    while battle.outcome().is_none() && battle.action_time() < MAX_ACTION_TIME {
+
+        match battle.pending_player() {
+            Some(player) => {
+                let slot = choose_ability(options.policy, &battle, player, &mut policy_rng);
+                let ability_count = battle.players()[player].abilities().len();
+
+                // The chosen ability may have nobody to target, e.g. a revive with nobody down
+                let used = battle.player_use_ability(slot, |event| events.push(event)) ||
+                    (0..ability_count)
+                        .filter(|&other| other != slot)
+                        .any(|other| battle.player_use_ability(other, |event| events.push(event)));
+
+                if !used {
+                    break;
+                }
+            },
+            None => battle.tick(DELTA, |event| events.push(event))
+        }
+
+        // Status ticks aren't credited to anyone
+        for event in events.drain(..) {
+            if let BattleEvents::Damage(report) = event {
+                match report.source {
+                    DamageSource::Attack(CombatantId::Player(i)) => result.player_damage[i] += report.amount,
+                    DamageSource::Attack(CombatantId::Enemy(i)) => result.enemy_damage[i] += report.amount,
+                    DamageSource::Status(_) => {}
+                }
+            }
+        }
+    }
+
+    result.outcome = battle.outcome();
+    result.duration = battle.action_time();
+    result.player_fatigue = total_fatigue(&battle);
+    result
+}
+
+fn describe_outcome(outcome: Option<Outcome>) -> &'static str {
+    match outcome {
+        Some(Outcome::Victory) => "victory",
+        Some(Outcome::Defeat) => "defeat",
+        Some(Outcome::Escaped) => "escaped",
+        None => "unfinished"
+    }
+}
+
+/// Replays each file and checks it reaches the outcome it was recorded with.
+fn verify_replays(paths: &[String]) -> bool {
+    let mut all_passed = true;
+
+    for path in paths {
+        let replay = match Replay::load(path) {
+            Ok(result) => result,
+            Err(e) => {
+                println!("FAIL {}: {}", path, e);
+                all_passed = false;
+                continue;
+            }
+        };
+
+        let expected = replay.outcome;
+        let mut player = ReplayPlayer::new(replay);
+        let battle = player.play_to_end();
+
+        if battle.outcome() == expected && !player.desynced() {
+            println!("PASS {}: {}", path, describe_outcome(expected));
+        } else {
+            println!(
+                "FAIL {}: expected {}, got {}{}",
+                path,
+                describe_outcome(expected),
+                describe_outcome(battle.outcome()),
+                if player.desynced() { " (commands out of sync)" } else { "" }
+            );
+            all_passed = false;
+        }
+    }
+
+    all_passed
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(result) => result,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    };
+
+    if options.replays.len() > 0 {
+        let all_passed = verify_replays(&options.replays);
+        process::exit(if all_passed { 0 } else { 1 });
+    }
+
+    let definitions_path = definitions::default_path();
+
+    let definitions = match Definitions::load(&definitions_path) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let enemy = match &options.enemy {
+        Some(name) => match definitions.enemies.iter().position(|e| &e.name == name) {
+            Some(i) => i,
+            None => {
+                eprintln!("No enemy named {} in {}", name, definitions_path.display());
+                process::exit(1);
+            }
+        },
+        None => 0
+    };
+
+    let mut totals = Totals::new(definitions.party.len(), options.enemy_count as usize);
+
+    for run in 0..options.runs {
+        let result = run_battle(&definitions, enemy, &options, options.seed + run as u64);
+        totals.add(&result);
+    }
+
+    let runs = options.runs as f32;
+    let enemy_name = &definitions.enemies[enemy].name;
+
+    println!("Runs: {}, seeds {}..{}", options.runs, options.seed, options.seed + options.runs as u64);
+    println!("Enemies: {} x {}", options.enemy_count, enemy_name);
+    println!("Win rate: {:.1}%", totals.victories as f32 / runs * 100.0);
+    println!("Losses: {}, escapes: {}, unfinished: {}", totals.defeats, totals.escapes, totals.unfinished);
+    println!("Average duration: {:.1} action time", totals.duration / runs);
+
+    println!("Average damage dealt:");
+    for (i, player) in definitions.party.iter().enumerate() {
+        println!("  {:<16} {:>8.1}", player.name, totals.player_damage[i] / runs);
+    }
+    for (i, damage) in totals.enemy_damage.iter().enumerate() {
+        println!("  {:<16} {:>8.1}", format!("{} {}", enemy_name, battle::enemy_marker(i)), damage / runs);
+    }
+
+    println!("Average fatigue remaining:");
+    for (i, player) in starting_party(&definitions).iter().enumerate() {
+        println!("  {:<16} {:>8.1} / {}", player.name, totals.player_fatigue[i] / runs, player.max_fatigue);
+    }
+}
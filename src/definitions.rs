@@ -0,0 +1,499 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::battle::ability::{AbilityDefinition, MAX_ABILITIES};
+use crate::battle::ai::{AiDefinition};
+use crate::battle::item::{Inventory, ItemCount, ItemDefinition, ItemDrop};
+use crate::battle::combatant::{BLOCK_DAMAGE_DIVISOR};
+use crate::battle::status::{StatusDefinition};
+use crate::party::{MAX_PARTY_SIZE};
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerDefinition {
+    pub name: String,
+    pub color: [f32; 4],
+    pub max_fatigue: i32,
+    pub first_action: f32,
+    pub block_hit_time_penalty: f32,
+    /// In hotbar order, the first is bound to key 1 and the tenth to key 0
+    pub abilities: Vec<AbilityDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_hit: Option<StatusDefinition>,
+    /// Hits taken while blocking are divided by this
+    #[serde(default = "default_block_damage_divisor")]
+    pub block_damage_divisor: i32,
+    /// Added to the balance rolled for each action
+    #[serde(default)]
+    pub balance_recovery: f32,
+    /// Names of what is worn from the start, at most one for each slot
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equipment: Vec<String>
+}
+
+fn default_block_damage_divisor() -> i32 {
+    BLOCK_DAMAGE_DIVISOR
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyDefinition {
+    pub name: String,
+    pub color: [f32; 4],
+    pub max_hp: i32,
+    pub first_action: f32,
+    pub attack_damage: i32,
+    pub attack_action_time: f32,
+    pub block_hit_time_penalty: f32,
+    /// Shared by every party member still standing when the battle is won
+    pub experience: u32,
+    /// Each rolled for separately
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drops: Vec<ItemDrop>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_hit: Option<StatusDefinition>,
+    #[serde(default)]
+    pub ai: AiDefinition
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EquipmentSlot {
+    Weapon,
+    Armour,
+    Accessory
+}
+
+impl EquipmentSlot {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EquipmentSlot::Weapon => "Weapon",
+            EquipmentSlot::Armour => "Armour",
+            EquipmentSlot::Accessory => "Accessory"
+        }
+    }
+}
+
+/// Worn by a party member, every modifier is added to what they'd have
+/// without it.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EquipmentDefinition {
+    pub name: String,
+    pub slot: EquipmentSlot,
+    /// Power of damaging abilities
+    #[serde(default)]
+    pub damage: i32,
+    /// Divisor applied to hits taken while blocking
+    #[serde(default)]
+    pub block_damage_divisor: i32,
+    /// Action time of damaging abilities
+    #[serde(default)]
+    pub attack_action_time: f32,
+    /// Fatigue cost of blocking abilities
+    #[serde(default)]
+    pub block_fatigue_cost: i32,
+    /// Balance rolled for each action
+    #[serde(default)]
+    pub balance_recovery: f32
+}
+
+/// A party member's growth at a level. Each entry holds totals from level 1
+/// rather than adding to the levels before it.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelDefinition {
+    /// Total experience needed to reach this level
+    pub experience: u32,
+    /// Added to max_fatigue
+    pub max_fatigue: i32,
+    /// Multiplies the power of damaging abilities
+    pub damage: f32,
+    /// Multiplies ability action times, so below 1.0 is faster
+    pub action_time: f32
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Definitions {
+    /// In starting formation order, front first
+    pub party: Vec<PlayerDefinition>,
+    pub enemies: Vec<EnemyDefinition>,
+    /// Starting with level 1
+    pub levels: Vec<LevelDefinition>,
+    pub items: Vec<ItemDefinition>,
+    pub equipment: Vec<EquipmentDefinition>,
+    /// What the party sets out with
+    pub inventory: Vec<ItemCount>
+}
+
+/// `resources/combatants.json`, looked for where ggez looks for resources:
+/// in the crate when run through cargo, otherwise beside the executable.
+pub fn default_path() -> PathBuf {
+    let mut path = match env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) => PathBuf::from(manifest_dir),
+        Err(_) => env::current_exe().ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .unwrap_or_default()
+    };
+
+    path.push("resources");
+    path.push("combatants.json");
+    path
+}
+
+pub enum DefinitionError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, serde_json::Error),
+    Invalid(PathBuf, String)
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DefinitionError::Io(path, e) =>
+                write!(f, "Failed to read {}: {}", path.display(), e),
+            DefinitionError::Parse(path, e) =>
+                write!(f, "Failed to parse {}: {}", path.display(), e),
+            DefinitionError::Invalid(path, reason) =>
+                write!(f, "Invalid definitions in {}: {}", path.display(), reason)
+        }
+    }
+}
+
+impl Definitions {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, DefinitionError> {
+        let path = path.as_ref();
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| DefinitionError::Io(path.to_path_buf(), e))?;
+
+        let definitions: Definitions = serde_json::from_str(&contents)
+            .map_err(|e| DefinitionError::Parse(path.to_path_buf(), e))?;
+
+        definitions.validate()
+            .map_err(|reason| DefinitionError::Invalid(path.to_path_buf(), reason))?;
+
+        Ok(definitions)
+    }
+
+    pub fn equipment(&self, name: &str) -> Option<&EquipmentDefinition> {
+        self.equipment.iter().find(|equipment| equipment.name == name)
+    }
+
+    /// Names are checked when loading, so any name taken from the definitions is found.
+    pub fn item(&self, name: &str) -> Option<&ItemDefinition> {
+        self.items.iter().find(|item| item.name == name)
+    }
+
+    pub fn starting_inventory(&self) -> Inventory {
+        let mut inventory = Inventory::default();
+
+        for count in &self.inventory {
+            if let Some(item) = self.item(&count.item) {
+                inventory.add(item, count.count);
+            }
+        }
+
+        inventory
+    }
+
+    fn validate(&self) -> Result<(), String> {
+
+        if self.party.is_empty() || self.party.len() > MAX_PARTY_SIZE {
+            return Err(format!("party must have between 1 and {} members", MAX_PARTY_SIZE));
+        }
+
+        if self.enemies.is_empty() {
+            return Err("enemies must have at least one archetype".to_string());
+        }
+
+        let mut names = HashSet::new();
+
+        // Items have their own names, they are never mixed up with combatants
+        let mut item_names = HashSet::new();
+
+        for item in &self.items {
+            check_unique_name(&mut item_names, &item.name, &format!("item \"{}\"", item.name))?;
+            item.validate()?;
+        }
+
+        for count in &self.inventory {
+            check_item_name(&item_names, &count.item, "inventory")?;
+        }
+
+        let mut equipment_names = HashSet::new();
+
+        for equipment in &self.equipment {
+            let context = format!("equipment \"{}\"", equipment.name);
+
+            check_unique_name(&mut equipment_names, &equipment.name, &context)?;
+
+            if equipment.balance_recovery < 0.0 || equipment.balance_recovery > 1.0 {
+                return Err(format!("{} balance_recovery must be between 0.0 and 1.0", context));
+            }
+        }
+
+        for player in &self.party {
+            let context = format!("party member \"{}\"", player.name);
+
+            check_unique_name(&mut names, &player.name, &context)?;
+            check_color(player.color, &context)?;
+            check_positive(player.max_fatigue as f32, "max_fatigue", &context)?;
+            check_not_negative(player.first_action, "first_action", &context)?;
+            check_not_negative(player.block_hit_time_penalty, "block_hit_time_penalty", &context)?;
+            check_abilities(&player.abilities, &context)?;
+            check_status(&player.on_hit, &context)?;
+            check_positive(player.block_damage_divisor as f32, "block_damage_divisor", &context)?;
+            check_not_negative(player.balance_recovery, "balance_recovery", &context)?;
+            self.check_equipment(&player.equipment, &context)?;
+        }
+
+        for enemy in &self.enemies {
+            let context = format!("enemy \"{}\"", enemy.name);
+
+            check_unique_name(&mut names, &enemy.name, &context)?;
+            check_color(enemy.color, &context)?;
+            check_positive(enemy.max_hp as f32, "max_hp", &context)?;
+            check_not_negative(enemy.first_action, "first_action", &context)?;
+            check_not_negative(enemy.attack_damage as f32, "attack_damage", &context)?;
+            check_positive(enemy.attack_action_time, "attack_action_time", &context)?;
+            check_not_negative(enemy.block_hit_time_penalty, "block_hit_time_penalty", &context)?;
+            check_status(&enemy.on_hit, &context)?;
+            enemy.ai.validate(&context)?;
+
+            for drop in &enemy.drops {
+                check_item_name(&item_names, &drop.item, &context)?;
+
+                if drop.chance < 0.0 || drop.chance > 1.0 {
+                    return Err(format!("{} drop chance must be between 0.0 and 1.0", context));
+                }
+            }
+        }
+
+        check_levels(&self.levels)?;
+
+        Ok(())
+    }
+
+    fn check_equipment(&self, names: &[String], context: &str) -> Result<(), String> {
+        let mut slots = Vec::new();
+
+        for name in names {
+            let slot = match self.equipment(name) {
+                Some(equipment) => equipment.slot,
+                None => return Err(format!("{} refers to equipment \"{}\" which is not defined", context, name))
+            };
+
+            if slots.contains(&slot) {
+                return Err(format!("{} has more than one {} equipped", context, slot.name()));
+            }
+
+            slots.push(slot);
+        }
+
+        Ok(())
+    }
+}
+
+fn check_unique_name(names: &mut HashSet<String>, name: &str, context: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("{} must have a name", context));
+    }
+
+    if !names.insert(name.to_string()) {
+        return Err(format!("{} is defined more than once", context));
+    }
+
+    Ok(())
+}
+
+fn check_item_name(item_names: &HashSet<String>, name: &str, context: &str) -> Result<(), String> {
+    if item_names.contains(name) {
+        Ok(())
+    } else {
+        Err(format!("{} refers to item \"{}\" which is not defined", context, name))
+    }
+}
+
+fn check_color(color: [f32; 4], context: &str) -> Result<(), String> {
+    for component in &color {
+        if *component < 0.0 || *component > 1.0 {
+            return Err(format!("{} color components must be between 0.0 and 1.0", context));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_abilities(abilities: &[AbilityDefinition], context: &str) -> Result<(), String> {
+    if abilities.is_empty() || abilities.len() > MAX_ABILITIES {
+        return Err(format!("{} must have between 1 and {} abilities", context, MAX_ABILITIES));
+    }
+
+    for ability in abilities {
+        ability.validate(context)?;
+    }
+
+    Ok(())
+}
+
+fn check_levels(levels: &[LevelDefinition]) -> Result<(), String> {
+    match levels.first() {
+        Some(level) if level.experience == 0 => {},
+        _ => return Err("levels must start with level 1 at 0 experience".to_string())
+    }
+
+    for (i, level) in levels.iter().enumerate() {
+        let context = format!("level {}", i + 1);
+
+        if i > 0 && level.experience <= levels[i - 1].experience {
+            return Err(format!("{} must need more experience than the level before", context));
+        }
+
+        check_not_negative(level.max_fatigue as f32, "max_fatigue", &context)?;
+        check_positive(level.damage, "damage", &context)?;
+        check_positive(level.action_time, "action_time", &context)?;
+    }
+
+    Ok(())
+}
+
+fn check_status(status: &Option<StatusDefinition>, context: &str) -> Result<(), String> {
+    match status {
+        Some(definition) => definition.validate(&format!("{} on_hit", context)),
+        None => Ok(())
+    }
+}
+
+fn check_positive(value: f32, field: &str, context: &str) -> Result<(), String> {
+    if value > 0.0 {
+        Ok(())
+    } else {
+        Err(format!("{} {} must be greater than 0", context, field))
+    }
+}
+
+fn check_not_negative(value: f32, field: &str, context: &str) -> Result<(), String> {
+    if value >= 0.0 {
+        Ok(())
+    } else {
+        Err(format!("{} {} must not be negative", context, field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn resource_definitions() -> Value {
+        let contents = fs::read_to_string(default_path()).unwrap();
+        serde_json::from_str(&contents).unwrap()
+    }
+
+    fn validate(json: Value) -> Result<(), String> {
+        let definitions: Definitions = serde_json::from_value(json).unwrap();
+        definitions.validate()
+    }
+
+    #[test]
+    fn resource_definitions_are_valid() {
+        assert!(Definitions::load(default_path()).is_ok());
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let mut json = resource_definitions();
+        let enemy = json["enemies"][0].clone();
+        json["enemies"].as_array_mut().unwrap().push(enemy);
+
+        assert_eq!(validate(json), Err("enemy \"Beetle\" is defined more than once".to_string()));
+    }
+
+    #[test]
+    fn party_and_enemy_names_share_one_namespace() {
+        let mut json = resource_definitions();
+        json["enemies"][0]["name"] = json!("Green");
+
+        assert_eq!(validate(json), Err("enemy \"Green\" is defined more than once".to_string()));
+    }
+
+    #[test]
+    fn empty_levels_are_rejected() {
+        let mut json = resource_definitions();
+        json["levels"] = json!([]);
+
+        assert_eq!(validate(json), Err("levels must start with level 1 at 0 experience".to_string()));
+    }
+
+    #[test]
+    fn levels_must_need_more_experience_each_time() {
+        let mut json = resource_definitions();
+        json["levels"][2]["experience"] = json["levels"][1]["experience"].clone();
+
+        assert_eq!(validate(json), Err("level 3 must need more experience than the level before".to_string()));
+    }
+
+    #[test]
+    fn non_positive_block_damage_divisor_is_rejected() {
+        let mut json = resource_definitions();
+        json["party"][0]["block_damage_divisor"] = json!(0);
+
+        assert_eq!(
+            validate(json),
+            Err("party member \"Green\" block_damage_divisor must be greater than 0".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_inventory_item_is_rejected() {
+        let mut json = resource_definitions();
+        json["inventory"][0]["item"] = json!("Elixir");
+
+        assert_eq!(validate(json), Err("inventory refers to item \"Elixir\" which is not defined".to_string()));
+    }
+
+    #[test]
+    fn unknown_drop_is_rejected() {
+        let mut json = resource_definitions();
+        json["enemies"][0]["drops"] = json!([{ "item": "Elixir", "chance": 0.5 }]);
+
+        assert_eq!(validate(json), Err("enemy \"Beetle\" refers to item \"Elixir\" which is not defined".to_string()));
+    }
+
+    #[test]
+    fn unknown_equipment_is_rejected() {
+        let mut json = resource_definitions();
+        json["party"][0]["equipment"] = json!(["Excalibur"]);
+
+        assert_eq!(
+            validate(json),
+            Err("party member \"Green\" refers to equipment \"Excalibur\" which is not defined".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_ability_is_rejected() {
+        let mut json = resource_definitions();
+        json["party"][0]["abilities"][0]["action_time"] = json!(0.0);
+
+        assert_eq!(
+            validate(json),
+            Err("party member \"Green\" ability \"Attack\" action_time must be greater than 0".to_string())
+        );
+    }
+
+    #[test]
+    fn errors_name_the_file() {
+        let error = Definitions::load("missing.json").err().unwrap();
+
+        assert!(error.to_string().starts_with("Failed to read missing.json"));
+    }
+}
@@ -1,8 +1,9 @@
 use ggez;
-use ggez::graphics::{self, DrawParam};
+use ggez::graphics::{self, Color, DrawParam, Font};
 use nalgebra::{Point2};
 use patchwork::{TileSet, TileParams};
-use rand::{random};
+use rand::{Rng, SeedableRng};
+use rand::rngs::{StdRng};
 use std::path::{PathBuf};
 use tiled_json_rs as tiled;
 
@@ -14,16 +15,22 @@ const EXPLORE_SPEED: f32 = 80.0;
 const DIAGONAL_FACTOR: f32 = 0.7071067811865475;
 const PLAYER_ANIMATION_FPS: f32 = 10.0;
 const MONSTER_ANIMATION_FPS: f32 = 10.0;
+/// Seconds before monsters the party escaped from can engage again
+const ESCAPE_GRACE_PERIOD: f32 = 3.0;
 
 struct Monster {
     id: u32,
+    kind: usize,
     position: Point2<f32>,
     in_battle: bool,
-    ko: bool
+    ko: bool,
+    marker: Option<(char, Color)>,
+    /// Seconds left before this monster can engage
+    grace_time: f32
 }
 
 pub enum ExploreEvents {
-    MonsterEncounter(u32)
+    MonsterEncounter(u32, usize)
 }
 
 enum Facing {
@@ -38,6 +45,7 @@ enum Facing {
 //  Don't worry about player behind world objects,
 //  it adds nothing to gameplay
 pub struct ExploreState {
+    rng: StdRng,
     tiles: TileSet<u32>,
     foreground_tiles: TileSet<u32>,
     tile_scale: f32,
@@ -52,7 +60,8 @@ pub struct ExploreState {
     player_frame_timer: f32,
     player_facing: Facing,
     monster_sprite: graphics::Image,
-    monster_frame_timer: f32
+    monster_frame_timer: f32,
+    monster_kinds: usize
 }
 
 struct SceneState {
@@ -62,7 +71,7 @@ struct SceneState {
 }
 
 impl SceneState {
-    fn new() -> Self {
+    fn new<R: Rng>(monster_kinds: usize, rng: &mut R) -> Self {
 
         let mut monsters = Vec::new();
         let mut monster_id = 1;
@@ -71,13 +80,16 @@ impl SceneState {
             for col in 0..8 {
                 let row = row as f32;
                 let col = col as f32;
-                let rand_x = 200.0 * col + random::<f32>() * 200.0;
-                let rand_y = 200.0 * row + random::<f32>() * 200.0;
+                let rand_x = 200.0 * col + rng.gen::<f32>() * 200.0;
+                let rand_y = 200.0 * row + rng.gen::<f32>() * 200.0;
                 monsters.push(Monster {
                     id: monster_id,
+                    kind: rng.gen_range(0, monster_kinds),
                     position: Point2::new(rand_x, rand_y),
                     in_battle: false,
-                    ko: false
+                    ko: false,
+                    marker: None,
+                    grace_time: 0.0
                 });
                 monster_id += 1;
             }
@@ -95,7 +107,15 @@ impl SceneState {
 }
 
 impl ExploreState {
-    pub fn new(ctx: &mut ggez::Context, screen_width: f32, screen_height: f32) -> ggez::GameResult<Self> {
+    pub fn new(
+        ctx: &mut ggez::Context,
+        screen_width: f32,
+        screen_height: f32,
+        monster_kinds: usize,
+        seed: u64
+    ) -> ggez::GameResult<Self> {
+
+        let mut rng = StdRng::seed_from_u64(seed);
 
         // Currently requires a symlink in the project root to the tileset
         // as the tiled library file paths are relative to the project root,
@@ -124,7 +144,10 @@ impl ExploreState {
 
         let tile_scale = screen_height / EXPLORE_HEIGHT;
 
+        let scene = SceneState::new(monster_kinds, &mut rng);
+
         Ok(Self {
+            rng: rng,
             tiles: tiles,
             foreground_tiles: foreground_tiles,
             tile_scale: tile_scale,
@@ -135,12 +158,13 @@ impl ExploreState {
             map: map,
             camera_x: 0.0,
             camera_y: 0.0,
-            scene: SceneState::new(),
+            scene: scene,
             player_sprite: graphics::Image::new(ctx, "/lidia_spritesheet_fix.png")?,
             player_frame_timer: 0.0,
             player_facing: Facing::Down,
             monster_sprite: graphics::Image::new(ctx, "/beetle_move_attack.png")?,
             monster_frame_timer: 0.0,
+            monster_kinds: monster_kinds
         })
 
     }
@@ -156,16 +180,47 @@ impl ExploreState {
 
     }
 
+    /// Labels a monster with the marker it has in battle.
+    pub fn notify_monster_marker(&mut self, id: u32, marker: char, color: Color) {
+
+        for monster in &mut self.scene.monsters {
+            if monster.id == id {
+                monster.marker = Some((marker, color));
+                break;
+            }
+        }
+
+    }
+
     pub fn notify_battle_end(&mut self) {
         self.scene.monsters.retain(|m| !m.ko);
 
         if self.scene.monsters.len() == 0 {
-            self.scene = SceneState::new();
+            self.scene = SceneState::new(self.monster_kinds, &mut self.rng);
         }
     }
 
     pub fn notify_player_defeat(&mut self) {
-        self.scene = SceneState::new();
+        self.scene = SceneState::new(self.monster_kinds, &mut self.rng);
+    }
+
+    /// Lets go of the monsters still standing in the battle, giving the
+    /// party a head start before they can engage again.
+    pub fn notify_escape(&mut self) {
+        self.scene.monsters.retain(|m| !m.ko);
+
+        for monster in &mut self.scene.monsters {
+            if monster.in_battle {
+                monster.in_battle = false;
+                monster.marker = None;
+                monster.grace_time = ESCAPE_GRACE_PERIOD;
+            }
+        }
+    }
+
+    /// Resting gives the monsters time to return.
+    pub fn notify_party_rest(&mut self) {
+        self.scene = SceneState::new(self.monster_kinds, &mut self.rng);
     }
 
     pub fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
@@ -204,10 +259,30 @@ impl ExploreState {
                         (monster.position.y - self.camera_y - 32.0) * self.tile_scale
                     ].into(),
                     scale: [self.tile_scale, self.tile_scale].into(),
+                    // Faded while it can't engage
+                    color: if monster.grace_time > 0.0 { Color::new(1.0, 1.0, 1.0, 0.5) } else { graphics::WHITE },
                     ..Default::default()
                 }
             )?;
 
+            if let Some((marker, color)) = monster.marker {
+                let mut marker_text = graphics::Text::new(marker.to_string());
+                marker_text.set_font(Font::default(), graphics::Scale::uniform(8.0 * self.tile_scale));
+                let half_width = marker_text.width(ctx) as f32 / 2.0;
+
+                graphics::draw(
+                    ctx,
+                    &marker_text,
+                    (
+                        Point2::new(
+                            (monster.position.x - self.camera_x) * self.tile_scale + self.tiles_offset - half_width,
+                            (monster.position.y - self.camera_y - 40.0) * self.tile_scale
+                        ),
+                        color
+                    )
+                )?;
+            }
+
         }
 
         let player_sprite_y_offset = match self.player_facing {
@@ -302,7 +377,9 @@ impl ExploreState {
 
         // Monster collision
         for monster in &mut self.scene.monsters {
-            if !monster.in_battle {
+            monster.grace_time = (monster.grace_time - delta).max(0.0);
+
+            if !monster.in_battle && monster.grace_time == 0.0 {
                 let dist_x = (monster.position.x - self.scene.x).abs();
                 let dist_y = (monster.position.y - self.scene.y).abs();
 
@@ -310,7 +387,7 @@ impl ExploreState {
 
                 if dist < 50.0 {
                     monster.in_battle = true;
-                    notify(ExploreEvents::MonsterEncounter(monster.id));
+                    notify(ExploreEvents::MonsterEncounter(monster.id, monster.kind));
                 }
             }
         }
@@ -2,4 +2,11 @@ pub mod action_frame;
 pub mod action_hotbar;
 pub mod action_timeline;
 pub mod balance_guage;
+pub mod battle_hud;
+pub mod character_screen;
+pub mod combat_log;
+pub mod damage_numbers;
+pub mod item_menu;
+pub mod notifications;
+pub mod party_panel;
 pub mod resource_guage;
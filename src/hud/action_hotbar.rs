@@ -1,12 +1,38 @@
 use ggez::{Context, GameResult};
-use ggez::graphics::{self, Font};
+use ggez::graphics::{self, Color, Font};
 use nalgebra::{Point2};
 
+use crate::battle::ability::{AbilityDefinition, MAX_ABILITIES};
+use crate::battle::item::{InventorySlot};
+use crate::palette;
 use crate::projector::{Projector};
 
-pub fn draw(ctx: &mut Context, projector: &Projector) -> GameResult {
+const SLOT_TEXT_WIDTH: f32 = 38.0;
 
-    for i in 0..10 {
+/// Slot under a point relative to the hotbar's projector.
+pub fn slot_at(x: f32, y: f32, projector: &Projector) -> Option<usize> {
+    if x < 0.0 || y < 0.0 || y > projector.scale(40.0) || x % projector.scale(50.0) > projector.scale(40.0) {
+        return None;
+    }
+
+    let slot = (x / projector.scale(50.0)) as usize;
+
+    if slot < MAX_ABILITIES {
+        Some(slot)
+    } else {
+        None
+    }
+}
+
+/// Abilities fill the first slots and items as many of the rest as they need.
+pub fn draw(
+    ctx: &mut Context,
+    projector: &Projector,
+    abilities: &[AbilityDefinition],
+    items: &[InventorySlot]
+) -> GameResult {
+
+    for i in 0..MAX_ABILITIES {
         let icon = graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::stroke(1.0),
@@ -30,22 +56,20 @@ pub fn draw(ctx: &mut Context, projector: &Projector) -> GameResult {
             None
         );
 
-        if i < 2 {
-            let mut hotkey_action = "Attack";
+        if let Some(ability) = abilities.get(i) {
+            queue_slot_name(ctx, projector, i, &ability.name, graphics::WHITE);
+        } else if let Some(slot) = items.get(i - abilities.len()) {
+            let color = if slot.count > 0 { graphics::WHITE } else { palette::GREY };
+            queue_slot_name(ctx, projector, i, &slot.item.name, color);
 
-            if i == 1 {
-                hotkey_action = "Block"
-            }
-
-            let mut action = graphics::Text::new(hotkey_action);
-            action.set_font(Font::default(), graphics::Scale::uniform(projector.scale(graphics::DEFAULT_FONT_SCALE * 0.8)));
-            let half_height = (action.height(ctx) / 2) as f32;
-            let centering_offset = (projector.scale(40.0) - action.width(ctx) as f32) / 2.0;
+            let mut count = graphics::Text::new(slot.count.to_string());
+            count.set_font(Font::default(), graphics::Scale::uniform(projector.scale(graphics::DEFAULT_FONT_SCALE * 0.5)));
+            let count_width = count.width(ctx) as f32;
             graphics::queue_text(
                 ctx,
-                &action,
-                Point2::new(centering_offset + projector.scale((i * 50) as f32), projector.scale(20.0) - half_height),
-                None
+                &count,
+                Point2::new(projector.scale(38.0 + (i * 50) as f32) - count_width, projector.scale(30.0)),
+                Some(color)
             );
         }
     }
@@ -60,3 +84,25 @@ pub fn draw(ctx: &mut Context, projector: &Projector) -> GameResult {
     Ok(())
 
 }
+
+fn queue_slot_name(ctx: &mut Context, projector: &Projector, i: usize, name: &str, color: Color) {
+    let mut action = graphics::Text::new(name.to_string());
+    let mut action_scale = projector.scale(graphics::DEFAULT_FONT_SCALE * 0.8);
+    action.set_font(Font::default(), graphics::Scale::uniform(action_scale));
+
+    // Shrink long names to fit inside the slot
+    let action_width = action.width(ctx) as f32;
+    if action_width > projector.scale(SLOT_TEXT_WIDTH) {
+        action_scale *= projector.scale(SLOT_TEXT_WIDTH) / action_width;
+        action.set_font(Font::default(), graphics::Scale::uniform(action_scale));
+    }
+
+    let half_height = (action.height(ctx) / 2) as f32;
+    let centering_offset = (projector.scale(40.0) - action.width(ctx) as f32) / 2.0;
+    graphics::queue_text(
+        ctx,
+        &action,
+        Point2::new(centering_offset + projector.scale((i * 50) as f32), projector.scale(20.0) - half_height),
+        Some(color)
+    );
+}
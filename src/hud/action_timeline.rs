@@ -1,5 +1,5 @@
 use ggez::{Context, GameResult};
-use ggez::graphics::{self, Color, Mesh, MeshBuilder};
+use ggez::graphics::{self, Color, Font, Mesh, MeshBuilder};
 use ggez::nalgebra::{Point2};
 use std::collections::HashMap;
 
@@ -10,7 +10,11 @@ pub struct ActionTimeline {
     time: f32,
     subject_colors: HashMap<i32, Color>,
     subject_times: HashMap<i32, f32>,
+    subject_labels: HashMap<i32, String>,
+    subject_rings: HashMap<i32, Color>,
     ordered_subjects: Vec<i32>,
+    /// Where a subject would move to, drawn as a ghost alongside it
+    preview: Option<(i32, f32)>,
     pub highlighted_subject: Option<i32>
 }
 
@@ -21,7 +25,10 @@ impl ActionTimeline {
             time: 0.0,
             subject_colors: HashMap::new(),
             subject_times: HashMap::new(),
+            subject_labels: HashMap::new(),
+            subject_rings: HashMap::new(),
             ordered_subjects: Vec::new(),
+            preview: None,
             highlighted_subject: None
         }
     }
@@ -64,6 +71,29 @@ impl ActionTimeline {
         self.ordered_subjects.insert(insert_position, subject_id);
     }
 
+    /// Text drawn over the subject's circle, see `draw_labels`.
+    pub fn set_subject_label<T: Into<String>>(&mut self, subject_id: i32, label: T) {
+        self.subject_labels.insert(subject_id, label.into());
+    }
+
+    /// Outlines the subject's circle, or removes the outline given None.
+    pub fn set_subject_ring(&mut self, subject_id: i32, color: Option<Color>) {
+        match color {
+            Some(color) => self.subject_rings.insert(subject_id, color),
+            None => self.subject_rings.remove(&subject_id)
+        };
+    }
+
+    /// Shows where `subject_id` would land if moved to `time`, replacing any
+    /// other preview.
+    pub fn set_preview(&mut self, subject_id: i32, time: f32) {
+        self.preview = Some((subject_id, time));
+    }
+
+    pub fn clear_preview(&mut self) {
+        self.preview = None;
+    }
+
     pub fn update(&mut self, time: f32) {
         self.time = time;
     }
@@ -72,10 +102,16 @@ impl ActionTimeline {
         self.ordered_subjects.retain(|&id| id != subject_id);
         self.subject_colors.remove(&subject_id);
         self.subject_times.remove(&subject_id);
+        self.subject_labels.remove(&subject_id);
+        self.subject_rings.remove(&subject_id);
 
         if Some(subject_id) == self.highlighted_subject {
             self.highlighted_subject = None;
         }
+
+        if self.preview.map_or(false, |(id, _)| id == subject_id) {
+            self.preview = None;
+        }
     }
 }
 
@@ -114,6 +150,84 @@ pub fn create_mesh(ctx: &mut Context, viewmodel: &ActionTimeline, projector: &Pr
         )?;
     }
 
+    for (id, position) in subject_positions(viewmodel, projector) {
+        ruler = ruler.circle(
+            graphics::DrawMode::fill(),
+            position,
+            projector.scale(8.0),
+            0.5,
+            if viewmodel.highlighted_subject == Some(id) { graphics::WHITE } else { viewmodel.subject_colors[&id] }
+        );
+
+        if let Some(&color) = viewmodel.subject_rings.get(&id) {
+            ruler = ruler.circle(
+                graphics::DrawMode::stroke(2.0),
+                position,
+                projector.scale(10.0),
+                0.5,
+                color
+            );
+        }
+    }
+
+    if let Some((id, time)) = viewmodel.preview {
+        // Kept on the ruler even when it would land past the end
+        let position = (time - viewmodel.time).clamp(0.0, 400.0);
+        let color = viewmodel.subject_colors[&id];
+
+        ruler = ruler.line(
+            &[
+                projector.coords(position, -10.0),
+                projector.coords(position, 0.0)
+            ],
+            2.0,
+            color
+        )?;
+
+        ruler = ruler.circle(
+            graphics::DrawMode::stroke(2.0),
+            projector.coords(position, -18.0),
+            projector.scale(8.0),
+            0.5,
+            color
+        );
+    }
+
+    return ruler.build(ctx);
+}
+
+/// Draws subject labels over the circles of a mesh drawn at `projector`'s origin.
+pub fn draw_labels(ctx: &mut Context, viewmodel: &ActionTimeline, projector: &Projector) -> GameResult {
+
+    for (id, position) in subject_positions(viewmodel, &projector.local()) {
+        if let Some(label) = viewmodel.subject_labels.get(&id) {
+            let mut text = graphics::Text::new(label.clone());
+            text.set_font(Font::default(), graphics::Scale::uniform(projector.scale(graphics::DEFAULT_FONT_SCALE * 0.7)));
+            let half_width = text.width(ctx) as f32 / 2.0;
+            let half_height = text.height(ctx) as f32 / 2.0;
+
+            graphics::queue_text(
+                ctx,
+                &text,
+                Point2::new(position.x - half_width, position.y - half_height),
+                Some(graphics::BLACK)
+            );
+        }
+    }
+
+    graphics::draw_queued_text(
+        ctx,
+        (projector.origin(),),
+        None,
+        graphics::FilterMode::Linear
+    )?;
+
+    Ok(())
+}
+
+/// Circle centres, stacked upwards where subjects would overlap.
+fn subject_positions(viewmodel: &ActionTimeline, projector: &Projector) -> Vec<(i32, Point2<f32>)> {
+    let mut positions = Vec::new();
     let mut previous_position = -100.0;
     let mut previous_stack = 0;
 
@@ -134,14 +248,8 @@ pub fn create_mesh(ctx: &mut Context, viewmodel: &ActionTimeline, projector: &Pr
         previous_position = subject_position;
         previous_stack = current_stack;
 
-        ruler = ruler.circle(
-            graphics::DrawMode::fill(),
-            projector.coords(subject_position, -18.0 * current_stack as f32),
-            projector.scale(8.0),
-            0.5,
-            if viewmodel.highlighted_subject == Some(*id) { graphics::WHITE } else { viewmodel.subject_colors[id] }
-        );
+        positions.push((*id, projector.coords(subject_position, -18.0 * current_stack as f32)));
     }
 
-    return ruler.build(ctx);
+    positions
 }
@@ -0,0 +1,672 @@
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, Font};
+
+use crate::battle::{BattleState, BattleEvents, DamageReport, DamageSource, PlayerAction};
+use crate::battle::ai::{EnemyMove};
+use crate::battle::combatant::{Combatant, CombatantId};
+use crate::battle::status::{StatusEffects, StatusKind};
+use crate::palette;
+use crate::projector::{Projector};
+use crate::hud::action_frame::{ActionFrame};
+use crate::hud::action_hotbar;
+use crate::hud::action_timeline::{self, ActionTimeline};
+use crate::hud::resource_guage::{self, ResourceGuage};
+use crate::hud::balance_guage::{self, BalanceGuage};
+use crate::hud::combat_log::{CombatLog};
+use crate::hud::damage_numbers::{DamageNumbers};
+use crate::hud::item_menu;
+
+/// Balance multiplier from which a hit counts as critical
+const CRITICAL_MULTIPLIER: f32 = 2.0;
+
+struct CombatantDisplay {
+    resource_guage: ResourceGuage,
+    balance_guage: BalanceGuage,
+    timeline_handle: i32,
+    action_frame: ActionFrame,
+    damage_numbers: DamageNumbers
+}
+
+impl CombatantDisplay {
+    fn new(combatant: &Combatant, timeline: &mut ActionTimeline) -> Self {
+        let color = Color::from(combatant.color);
+
+        Self {
+            resource_guage: ResourceGuage::new(
+                combatant.max_resource as f32,
+                combatant.current_resource as f32,
+                color
+            ),
+            balance_guage: BalanceGuage::new(combatant.current_balance),
+            timeline_handle: timeline.add_subject(color, combatant.next_action_time),
+            action_frame: ActionFrame::new(color),
+            damage_numbers: DamageNumbers::new()
+        }
+    }
+
+    fn update(&mut self, time: f32, delta: f32) {
+        self.action_frame.update_time(time);
+        self.damage_numbers.update_time(time);
+        balance_guage::update(&mut self.balance_guage, delta);
+        resource_guage::update(&mut self.resource_guage, delta);
+    }
+}
+
+/// Presents a `BattleState`, kept in step by feeding it the battle's events.
+pub struct BattleHud {
+    time: f32,
+    timeline: ActionTimeline,
+    players: Vec<CombatantDisplay>,
+    enemies: Vec<CombatantDisplay>,
+    combat_log: CombatLog,
+    combat_log_hovered: bool,
+    item_menu_open: bool,
+    pub hovered_enemy: Option<usize>,
+    pub hovered_player: Option<usize>,
+    /// Player whose next action is being chosen ahead of their turn
+    selected_player: Option<usize>,
+    /// Inventory slot under the mouse in the item menu
+    pub hovered_item: Option<usize>,
+    pub hovered_hotbar_slot: Option<usize>
+}
+
+impl BattleHud {
+    pub fn new(battle: &BattleState, font: Font) -> Self {
+        let mut hud = Self {
+            time: 0.0,
+            timeline: ActionTimeline::new(),
+            players: Vec::new(),
+            enemies: Vec::new(),
+            combat_log: CombatLog::new(font),
+            combat_log_hovered: false,
+            item_menu_open: false,
+            hovered_enemy: None,
+            hovered_player: None,
+            selected_player: None,
+            hovered_item: None,
+            hovered_hotbar_slot: None
+        };
+
+        for player in battle.players() {
+            let display = CombatantDisplay::new(&player.combatant, &mut hud.timeline);
+
+            // Knocked out before the battle began, so never due a turn
+            if !player.combatant.is_standing() {
+                hud.timeline.remove_subject(display.timeline_handle);
+            }
+
+            hud.players.push(display);
+        }
+
+        for i in 0..battle.enemies().len() {
+            hud.add_enemy(i, battle);
+            hud.combat_log.handle_event(&BattleEvents::EnemyJoins(i), battle);
+        }
+
+        hud
+    }
+
+    fn add_enemy(&mut self, i: usize, battle: &BattleState) {
+        let enemy = &battle.enemies()[i];
+
+        let display = CombatantDisplay::new(&enemy.combatant, &mut self.timeline);
+        self.timeline.set_subject_label(display.timeline_handle, enemy.marker.to_string());
+        self.enemies.push(display);
+        self.update_intent(i, battle);
+    }
+
+    /// Rings the enemy's timeline marker in the colour of the player it means
+    /// to attack, or white if it means to block.
+    fn update_intent(&mut self, i: usize, battle: &BattleState) {
+        let ring = intent_label(battle, i).map(|(_, color)| color);
+        self.timeline.set_subject_ring(self.enemies[i].timeline_handle, ring);
+    }
+
+    pub fn handle_event(&mut self, event: &BattleEvents, battle: &BattleState) {
+
+        self.combat_log.handle_event(event, battle);
+
+        match event {
+            BattleEvents::End(_) => {},
+            BattleEvents::EnemyJoins(i) => {
+                self.add_enemy(*i, battle);
+            },
+            BattleEvents::EnemyAttacks(i) => {
+                self.enemies[*i].action_frame.activate("Attack");
+                self.update_schedule(CombatantId::Enemy(*i), battle);
+            },
+            BattleEvents::EnemyIntent(i) => {
+                self.update_intent(*i, battle);
+            },
+            BattleEvents::PlayerUsesAbility(i, slot) => {
+                let ability = &battle.players()[*i].abilities()[*slot];
+
+                self.players[*i].action_frame.activate(ability.name.clone());
+                self.update_schedule(CombatantId::Player(*i), battle);
+            },
+            BattleEvents::PlayerUsesItem(i, index) => {
+                let item = &battle.inventory().slots()[*index].item;
+
+                self.players[*i].action_frame.activate(item.name.clone());
+                self.update_schedule(CombatantId::Player(*i), battle);
+                self.item_menu_open = false;
+                self.hovered_item = None;
+            },
+            BattleEvents::Blocks(id) => {
+                self.update_schedule(*id, battle);
+            },
+            BattleEvents::BlocksHit(id) => {
+                self.display_mut(*id).action_frame.activate("Block");
+                self.update_schedule(*id, battle);
+            },
+            BattleEvents::Damage(report) => {
+                self.display_mut(report.target).damage_numbers.spawn(report.amount.to_string(), damage_color(report));
+                self.update_resource(report.target, battle);
+            },
+            BattleEvents::FatigueCost(id, _) | BattleEvents::Recovers(id, _) => {
+                self.update_resource(*id, battle);
+            },
+            BattleEvents::EscapeFailed(i) => {
+                self.players[*i].action_frame.activate("Escape");
+                self.update_schedule(CombatantId::Player(*i), battle);
+            },
+            BattleEvents::BalanceRestored(id) => {
+                self.display_mut(*id).balance_guage.update(battle.combatant(*id).current_balance);
+            },
+            BattleEvents::Down(id) => {
+                let timeline_handle = self.display_mut(*id).timeline_handle;
+                self.timeline.remove_subject(timeline_handle);
+
+                if let CombatantId::Enemy(i) = id {
+                    if self.hovered_enemy == Some(*i) {
+                        self.hovered_enemy = None;
+                    }
+                }
+            },
+            BattleEvents::Revived(id, _) => {
+                let combatant = battle.combatant(*id);
+                let timeline_handle = self.timeline.add_subject(Color::from(combatant.color), combatant.next_action_time);
+
+                self.display_mut(*id).timeline_handle = timeline_handle;
+                self.update_resource(*id, battle);
+            },
+            BattleEvents::StatusApplied(id, kind) => {
+                // Stun moves the next action so the timeline has to follow
+                self.display_mut(*id).action_frame.activate(kind.name());
+                self.update_schedule(*id, battle);
+            },
+            BattleEvents::StatusExpired(_, _) => {}
+        }
+    }
+
+    fn display_mut(&mut self, id: CombatantId) -> &mut CombatantDisplay {
+        match id {
+            CombatantId::Player(i) => &mut self.players[i],
+            CombatantId::Enemy(i) => &mut self.enemies[i]
+        }
+    }
+
+    fn update_resource(&mut self, id: CombatantId, battle: &BattleState) {
+        let current_resource = battle.combatant(id).current_resource;
+        self.display_mut(id).resource_guage.update(current_resource as f32);
+    }
+
+    fn update_schedule(&mut self, id: CombatantId, battle: &BattleState) {
+        let combatant = battle.combatant(id);
+        let display = self.display_mut(id);
+
+        display.balance_guage.update(combatant.current_balance);
+        let timeline_handle = display.timeline_handle;
+        self.timeline.update_subject(timeline_handle, combatant.next_action_time);
+    }
+
+    pub fn update(&mut self, battle: &BattleState, delta: f32) {
+        // Once their turn comes, or they go down, there is nothing left to queue
+        if let Some(i) = self.selected_player {
+            if !battle.can_queue_action(i) {
+                self.clear_player_selection();
+            }
+        }
+
+        self.time += delta;
+        self.timeline.update(battle.action_time());
+        self.update_preview(battle);
+
+        for display in self.players.iter_mut().chain(self.enemies.iter_mut()) {
+            display.update(self.time, delta);
+        }
+
+        self.combat_log.update_time(self.time);
+    }
+
+    /// Ghost on the timeline for when the commanded player would act again
+    /// after the hotbar slot or item under the mouse.
+    fn update_preview(&mut self, battle: &BattleState) {
+        let preview = self.commanded_player(battle).and_then(|i| {
+            let action = match (self.hovered_hotbar_slot, self.hovered_item) {
+                (Some(slot), _) => battle.hotbar_action(i, slot),
+                (None, Some(index)) => PlayerAction::Item(index),
+                (None, None) => return None
+            };
+
+            battle.preview_next_action_time(i, action).map(|time| (i, time))
+        });
+
+        match preview {
+            Some((i, time)) => self.timeline.set_preview(self.players[i].timeline_handle, time),
+            None => self.timeline.clear_preview()
+        }
+    }
+
+    /// A player picked ahead of their turn takes over the controls until they
+    /// are put back.
+    fn commanded_player(&self, battle: &BattleState) -> Option<usize> {
+        match self.selected_player {
+            Some(i) => Some(i),
+            None if battle.player_move_pending() => battle.pending_player(),
+            None => None
+        }
+    }
+
+    pub fn handle_mouse_move(&mut self, x: f32, y: f32, projector: &Projector, battle: &BattleState) {
+        self.timeline.highlighted_subject = None;
+        self.hovered_enemy = None;
+        self.hovered_player = None;
+        self.hovered_item = None;
+        self.hovered_hotbar_slot = None;
+        self.combat_log_hovered = self.combat_log.contains(x, y, projector);
+
+        if self.commanded_player(battle).is_some() {
+            let hotbar_projector = self.hotbar_projector(projector);
+
+            self.hovered_hotbar_slot = action_hotbar::slot_at(
+                hotbar_projector.to_local_x(x),
+                hotbar_projector.to_local_y(y),
+                &hotbar_projector
+            );
+        }
+
+        if self.item_menu_open {
+            let items = battle.inventory().slots();
+            let menu_projector = self.item_menu_projector(projector, battle);
+
+            self.hovered_item = item_menu::row_at(
+                menu_projector.to_local_x(x),
+                menu_projector.to_local_y(y),
+                &menu_projector,
+                items
+            );
+        }
+
+        if y < projector.scale(70.0) {
+            for (i, enemy) in battle.enemies().iter().enumerate().rev() {
+                if projector.top_right((i + 1) as f32 * 140.0).to_local_x(x) > 0.0 && enemy.combatant.is_standing() {
+                    self.hovered_enemy = Some(i);
+                    self.timeline.highlighted_subject = Some(self.enemies[i].timeline_handle);
+                }
+            }
+        }
+
+        let panels_projector = projector.bottom_left(90.0);
+
+        if panels_projector.to_local_y(y) > 0.0 {
+            for i in 0..self.players.len() {
+                let panel_x = panels_projector.to_local_x(x) - projector.scale(i as f32 * 140.0);
+
+                if panel_x > 0.0 && panel_x < projector.scale(120.0) {
+                    self.hovered_player = Some(i);
+                    self.timeline.highlighted_subject = Some(self.players[i].timeline_handle);
+                }
+            }
+        }
+    }
+
+    pub fn toggle_item_menu(&mut self) {
+        self.item_menu_open = !self.item_menu_open;
+        self.hovered_item = None;
+    }
+
+    pub fn selected_player(&self) -> Option<usize> {
+        self.selected_player
+    }
+
+    /// Picks a player to queue an action for, or puts them back if already picked.
+    pub fn toggle_player_selection(&mut self, i: usize, battle: &BattleState) {
+        if self.selected_player == Some(i) {
+            self.clear_player_selection();
+        } else if battle.can_queue_action(i) {
+            self.selected_player = Some(i);
+            self.item_menu_open = false;
+            self.hovered_item = None;
+        }
+    }
+
+    pub fn clear_player_selection(&mut self) {
+        self.selected_player = None;
+        self.item_menu_open = false;
+        self.hovered_item = None;
+    }
+
+    fn controls_projector(&self, projector: &Projector) -> Projector {
+        // The hotbar and timeline are centred in whatever room the party leaves
+        projector.inset_left(self.players.len() as f32 * 140.0)
+    }
+
+    fn hotbar_projector(&self, projector: &Projector) -> Projector {
+        self.controls_projector(projector)
+            .bottom_left(150.0)
+            .centered_horizontal(490.0)
+    }
+
+    /// Beside the hotbar, lined up with its bottom edge.
+    fn item_menu_projector(&self, projector: &Projector, battle: &BattleState) -> Projector {
+        let menu_height = item_menu::height(battle.inventory().slots());
+
+        self.hotbar_projector(projector).local_relative(510.0, 40.0 - menu_height)
+    }
+
+    /// Scrolls the combat log while the mouse is over it.
+    pub fn handle_mouse_wheel(&mut self, y: f32) {
+        if self.combat_log_hovered {
+            self.combat_log.scroll(y as i32);
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, projector: &Projector, battle: &BattleState) -> GameResult {
+
+        let controls_projector = self.controls_projector(projector);
+
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            let combatant = &battle.enemies()[i].combatant;
+
+            draw_enemy_display(
+                ctx,
+                enemy,
+                combatant,
+                // -20.0 is to remove the final right margin included in the 140.0
+                &projector.top_right((i + 1) as f32 * 140.0 - 20.0),
+                i == battle.target_enemy(),
+                if combatant.is_standing() { intent_label(battle, i) } else { None }
+            )?;
+        }
+
+        if let Some(i) = self.commanded_player(battle) {
+            action_hotbar::draw(
+                ctx,
+                &self.hotbar_projector(projector),
+                battle.players()[i].abilities(),
+                battle.inventory().slots()
+            )?;
+
+            draw_command_keys(
+                ctx,
+                &self.hotbar_projector(projector).local_relative(0.0, 46.0),
+                match self.selected_player {
+                    Some(_) => None,
+                    None => Some(battle.escape_chance())
+                }
+            )?;
+
+            if self.item_menu_open {
+                item_menu::draw(
+                    ctx,
+                    &self.item_menu_projector(projector, battle),
+                    battle.inventory().slots(),
+                    self.hovered_item
+                )?;
+            }
+        }
+
+        // Front of the formation on the left
+        for (i, player) in self.players.iter().enumerate() {
+            let highlight = if self.selected_player == Some(i) {
+                Some(palette::BLUE)
+            } else if battle.player_move_pending() && battle.pending_player() == Some(i) {
+                Some(palette::YELLOW)
+            } else {
+                None
+            };
+
+            let queued_action = battle.players()[i].queued_action()
+                .map(|queued| action_name(battle, i, queued.action));
+
+            draw_player_display(
+                ctx,
+                player,
+                &battle.players()[i].combatant,
+                &projector.bottom_left(90.0)
+                    .local_relative(i as f32 * 140.0, 0.0),
+                highlight,
+                queued_action
+            )?;
+        }
+
+        let timeline_mesh = action_timeline::create_mesh(
+            ctx,
+            &self.timeline,
+            &projector.local()
+        )?;
+
+        let timeline_projector = controls_projector.bottom_left(170.0).centered_horizontal(400.0);
+
+        graphics::draw(
+            ctx,
+            &timeline_mesh,
+            (timeline_projector.origin(),)
+        )?;
+
+        action_timeline::draw_labels(ctx, &self.timeline, &timeline_projector)?;
+
+        self.combat_log.draw(ctx, projector)?;
+
+        Ok(())
+    }
+}
+
+fn draw_enemy_display(
+    ctx: &mut Context,
+    enemy: &CombatantDisplay,
+    combatant: &Combatant,
+    project: &Projector,
+    is_highlighted: bool,
+    intent: Option<(String, Color)>
+) -> GameResult {
+    let enemy_hp_guage = resource_guage::create_mesh(ctx, &enemy.resource_guage, &project.local())?;
+    let enemy_balance_guage = balance_guage::create_mesh(ctx, &enemy.balance_guage, &project.local())?;
+    graphics::draw(ctx, &enemy_hp_guage, (project.coords(10.0, 10.0),))?;
+    graphics::draw(ctx, &enemy_balance_guage, (project.coords(10.0, 40.0),))?;
+
+    let mut name_text = graphics::Text::new(combatant.name.clone());
+    name_text.set_font(Font::default(), graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 0.8)));
+    graphics::draw(ctx, &name_text, (project.coords(10.0, -14.0),))?;
+
+    if is_highlighted {
+        let enemy_highlight = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::stroke(2.0),
+            graphics::Rect {
+                x: 0.0,
+                y: 0.0,
+                w: project.scale(120.0),
+                h: project.scale(70.0)
+            },
+            palette::GREY
+        )?;
+        graphics::draw(
+            ctx,
+            &enemy_highlight,
+            (project.origin(),)
+        )?;
+    }
+
+    if combatant.is_blocking {
+        draw_block_icon(ctx, &project.local_relative(10.0, 80.0))?;
+    }
+
+    // Stacked down the right of the action frame to stay clear of the next enemy
+    draw_status_icons(ctx, &combatant.statuses, &project.local_relative(100.0, 80.0), 0.0, 14.0)?;
+
+    enemy.action_frame.draw(ctx, &project.local_relative(30.0, 80.0))?;
+
+    // Below the action frame so the two never cover each other
+    if let Some((label, color)) = intent {
+        let mut intent_text = graphics::Text::new(format!("Next: {}", label));
+        intent_text.set_font(Font::default(), graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 0.8)));
+        graphics::draw(ctx, &intent_text, (project.coords(10.0, 146.0), color))?;
+    }
+
+    enemy.damage_numbers.draw(ctx, &project.local_relative(50.0, 30.0))?;
+
+    Ok(())
+}
+
+fn draw_player_display(
+    ctx: &mut Context,
+    player: &CombatantDisplay,
+    combatant: &Combatant,
+    project: &Projector,
+    highlight: Option<Color>,
+    queued_action: Option<String>
+) -> GameResult {
+
+    let player_fatigue_guage = resource_guage::create_mesh(ctx, &player.resource_guage, &project.local())?;
+    let player_balance_guage = balance_guage::create_mesh(ctx, &player.balance_guage, &project.local())?;
+
+    graphics::draw(ctx, &player_fatigue_guage, (project.coords(10.0, 10.0),))?;
+    graphics::draw(ctx, &player_balance_guage, (project.coords(10.0, 40.0),))?;
+
+    if let Some(color) = highlight {
+        let player_highlight = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::stroke(2.0),
+            graphics::Rect {
+                x: 0.0,
+                y: 0.0,
+                w: project.scale(120.0),
+                h: project.scale(70.0)
+            },
+            color
+        )?;
+        graphics::draw(
+            ctx,
+            &player_highlight,
+            (project.origin(),)
+        )?;
+    }
+
+    if let Some(name) = queued_action {
+        let mut queued_text = graphics::Text::new(format!("Next: {}", name));
+        queued_text.set_font(Font::default(), graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 0.8)));
+        graphics::draw(ctx, &queued_text, (project.coords(10.0, -16.0), palette::BLUE))?;
+    }
+
+    if combatant.is_blocking {
+        draw_block_icon(ctx, &project.local_relative(10.0, 80.0))?;
+    }
+
+    draw_status_icons(ctx, &combatant.statuses, &project.local_relative(24.0, 80.0), 14.0, 0.0)?;
+
+    player.action_frame.draw(ctx, &project.local_relative(30.0, -70.0))?;
+
+    // Beside the action frame so both stay readable
+    player.damage_numbers.draw(ctx, &project.local_relative(94.0, -20.0))?;
+
+    Ok(())
+}
+
+/// Keys for the commands that aren't on the hotbar. Escaping can't be
+/// queued so it is only offered on a player's turn.
+fn draw_command_keys(ctx: &mut Context, project: &Projector, escape_chance: Option<f32>) -> GameResult {
+    let mut text = graphics::Text::new(match escape_chance {
+        Some(chance) => format!("[I] Items    [E] Escape ({:.0}%)", chance * 100.0),
+        None => "[I] Items    [X] Cancel queued".to_string()
+    });
+    text.set_font(Font::default(), graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 0.8)));
+    graphics::draw(ctx, &text, (project.origin(), palette::GREY))?;
+
+    Ok(())
+}
+
+fn draw_block_icon(ctx: &mut Context, project: &Projector) -> GameResult {
+    let block_icon = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect {
+            x: 0.0,
+            y: 0.0,
+            w: project.scale(10.0),
+            h: project.scale(10.0)
+        },
+        graphics::WHITE
+    )?;
+    graphics::draw(ctx, &block_icon, (project.origin(),))?;
+
+    Ok(())
+}
+
+fn draw_status_icons(
+    ctx: &mut Context,
+    statuses: &StatusEffects,
+    project: &Projector,
+    step_x: f32,
+    step_y: f32
+) -> GameResult {
+    let mut offset = 0.0;
+
+    for status in statuses.iter() {
+        let status_icon = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect {
+                x: 0.0,
+                y: 0.0,
+                w: project.scale(10.0),
+                h: project.scale(10.0)
+            },
+            status_color(status.kind)
+        )?;
+        graphics::draw(ctx, &status_icon, (project.coords(offset * step_x, offset * step_y),))?;
+        offset += 1.0;
+    }
+
+    Ok(())
+}
+
+/// What an enemy means to do next and the colour to show it in.
+fn intent_label(battle: &BattleState, enemy_index: usize) -> Option<(String, Color)> {
+    match battle.enemies()[enemy_index].intent()? {
+        EnemyMove::Attack(i) => {
+            let target = &battle.players()[i].combatant;
+            Some((format!("Attack {}", target.name), Color::from(target.color)))
+        },
+        EnemyMove::Block => Some(("Block".to_string(), graphics::WHITE))
+    }
+}
+
+fn action_name(battle: &BattleState, player_index: usize, action: PlayerAction) -> String {
+    match action {
+        PlayerAction::Ability(slot) => battle.players()[player_index].abilities()[slot].name.clone(),
+        PlayerAction::Item(index) => battle.inventory().slots()[index].item.name.clone()
+    }
+}
+
+/// Blocked hits take priority over how well balanced the attacker was.
+fn damage_color(report: &DamageReport) -> Color {
+    match report.source {
+        DamageSource::Status(kind) => status_color(kind),
+        DamageSource::Attack(_) if report.block_reduction > 0 => palette::BLUE,
+        DamageSource::Attack(_) if report.balance_multiplier >= CRITICAL_MULTIPLIER => palette::YELLOW,
+        DamageSource::Attack(_) if report.balance_multiplier < 1.0 => palette::GREY,
+        DamageSource::Attack(_) => graphics::WHITE
+    }
+}
+
+fn status_color(kind: StatusKind) -> Color {
+    match kind {
+        StatusKind::Poison => palette::PURPLE,
+        StatusKind::Stun => palette::YELLOW,
+        StatusKind::Haste => palette::BLUE,
+        StatusKind::Slow => palette::GREY,
+        StatusKind::Regen => palette::GREEN
+    }
+}
@@ -0,0 +1,70 @@
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, Font};
+
+use crate::definitions::{EquipmentSlot, LevelDefinition};
+use crate::palette;
+use crate::party::{PartyMember};
+use crate::projector::{Projector};
+
+const COLUMN_WIDTH: f32 = 260.0;
+const SCREEN_HEIGHT: f32 = 300.0;
+const LINE_HEIGHT: f32 = 22.0;
+const SLOTS: [EquipmentSlot; 3] = [EquipmentSlot::Weapon, EquipmentSlot::Armour, EquipmentSlot::Accessory];
+
+/// Each party member's level, equipment and what that equipment adds up to,
+/// side by side in formation order.
+pub fn draw(
+    ctx: &mut Context,
+    project: &Projector,
+    font: Font,
+    party: &[PartyMember],
+    levels: &[LevelDefinition]
+) -> GameResult {
+    let screen_projector = project.centered(COLUMN_WIDTH * party.len() as f32, SCREEN_HEIGHT);
+
+    let background = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect {
+            x: 0.0,
+            y: 0.0,
+            w: screen_projector.scale(COLUMN_WIDTH * party.len() as f32),
+            h: screen_projector.scale(SCREEN_HEIGHT)
+        },
+        Color::new(0.0, 0.0, 0.0, 0.8)
+    )?;
+    graphics::draw(ctx, &background, (screen_projector.origin(),))?;
+
+    for (i, member) in party.iter().enumerate() {
+        let column_projector = screen_projector.local_relative(i as f32 * COLUMN_WIDTH + 10.0, 10.0);
+        let mut lines: Vec<(String, Color)> = Vec::new();
+
+        lines.push((member.definition.name.clone(), Color::from(member.definition.color)));
+        lines.push((format!("Level {}  ({} xp)", member.level, member.experience), graphics::WHITE));
+        lines.push((format!("Fatigue {}/{}", member.fatigue, member.max_fatigue(levels)), graphics::WHITE));
+        lines.push((String::new(), graphics::WHITE));
+
+        for slot in SLOTS.iter() {
+            let equipped = member.equipment.iter()
+                .find(|equipment| equipment.slot == *slot)
+                .map_or("-".to_string(), |equipment| equipment.name.clone());
+
+            lines.push((format!("{}: {}", slot.name(), equipped), graphics::WHITE));
+        }
+
+        lines.push((String::new(), graphics::WHITE));
+        lines.push((format!("Damage {:+}", member.equipment_total(|e| e.damage)), palette::GREY));
+        lines.push((format!("Block divisor {:+}", member.equipment_total(|e| e.block_damage_divisor)), palette::GREY));
+        lines.push((format!("Attack time {:+}", member.equipment_total(|e| e.attack_action_time)), palette::GREY));
+        lines.push((format!("Block cost {:+}", member.equipment_total(|e| e.block_fatigue_cost)), palette::GREY));
+        lines.push((format!("Balance {:+.2}", member.equipment_total(|e| e.balance_recovery)), palette::GREY));
+
+        for (row, (line, color)) in lines.into_iter().enumerate() {
+            let mut text = graphics::Text::new(line);
+            text.set_font(font, graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 1.2)));
+            graphics::draw(ctx, &text, (column_projector.coords(0.0, row as f32 * LINE_HEIGHT), color))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, Font};
+use nalgebra::{Point2};
+
+use crate::battle::{BattleState, BattleEvents, DamageReport, DamageSource};
+use crate::battle::combatant::{CombatantId};
+use crate::palette;
+use crate::projector::{Projector};
+
+pub const LOG_WIDTH: f32 = 380.0;
+const VISIBLE_LINES: usize = 10;
+const LINE_HEIGHT: f32 = 18.0;
+const FADE_DELAY: f32 = 6.0;
+const FADE_DURATION: f32 = 2.0;
+
+struct LogLine {
+    text: String,
+    color: Color,
+    time: f32
+}
+
+/// Lists what happened in the battle, newest at the bottom. Lines fade out
+/// with age unless the log has been scrolled back.
+pub struct CombatLog {
+    font: Font,
+    time: f32,
+    lines: Vec<LogLine>,
+    /// Number of lines scrolled back from the newest
+    scroll: usize
+}
+
+impl CombatLog {
+    pub fn new(font: Font) -> Self {
+        Self {
+            font: font,
+            time: 0.0,
+            lines: Vec::new(),
+            scroll: 0
+        }
+    }
+
+    pub fn update_time(&mut self, current_time: f32) {
+        self.time = current_time;
+    }
+
+    /// Positive `lines` scrolls back towards older lines.
+    pub fn scroll(&mut self, lines: i32) {
+        let max_scroll = self.lines.len().saturating_sub(VISIBLE_LINES) as i32;
+        self.scroll = (self.scroll as i32 + lines).max(0).min(max_scroll) as usize;
+    }
+
+    pub fn contains(&self, x: f32, y: f32, project: &Projector) -> bool {
+        let local_x = project.to_local_x(x);
+        let local_y = project.to_local_y(y);
+
+        local_x >= 0.0 && local_x <= project.scale(LOG_WIDTH) &&
+            local_y >= 0.0 && local_y <= project.scale(LINE_HEIGHT * VISIBLE_LINES as f32)
+    }
+
+    pub fn handle_event(&mut self, event: &BattleEvents, battle: &BattleState) {
+        let name = |id: CombatantId| battle.combatant(id).name.clone();
+
+        match event {
+            BattleEvents::End(_) => {},
+            BattleEvents::EnemyJoins(i) => {
+                self.add(format!("{} joins the battle", name(CombatantId::Enemy(*i))), graphics::WHITE);
+            },
+            BattleEvents::EnemyAttacks(_) | BattleEvents::EnemyIntent(_) => {},
+            BattleEvents::PlayerUsesAbility(i, slot) => {
+                let ability = &battle.players()[*i].abilities()[*slot];
+                self.add(format!("{} uses {}", name(CombatantId::Player(*i)), ability.name), graphics::WHITE);
+            },
+            BattleEvents::PlayerUsesItem(i, index) => {
+                let item = &battle.inventory().slots()[*index].item;
+                self.add(format!("{} uses a {}", name(CombatantId::Player(*i)), item.name), graphics::WHITE);
+            },
+            BattleEvents::Blocks(id) => {
+                self.add(format!("{} blocks", name(*id)), graphics::WHITE);
+            },
+            BattleEvents::BlocksHit(id) => {
+                let penalty = battle.combatant(*id).block_hit_time_penalty();
+                self.add(format!("{} blocks, delayed {}", name(*id), penalty), graphics::WHITE);
+            },
+            BattleEvents::Damage(report) => {
+                self.add(describe_damage(report, &name), Color::from(battle.combatant(report.target).color));
+            },
+            BattleEvents::FatigueCost(id, amount) => if *amount > 0 {
+                self.add(format!("{} spends {} fatigue", name(*id), amount), palette::GREY);
+            },
+            BattleEvents::Recovers(id, amount) => {
+                self.add(format!("{} recovers {}", name(*id), amount), palette::GREY);
+            },
+            BattleEvents::EscapeFailed(i) => {
+                self.add(format!("{} fails to find a way out", name(CombatantId::Player(*i))), graphics::WHITE);
+            },
+            BattleEvents::BalanceRestored(id) => {
+                self.add(format!("{} steadies", name(*id)), palette::GREY);
+            },
+            BattleEvents::Down(id) => {
+                self.add(format!("{} is down", name(*id)), graphics::WHITE);
+            },
+            BattleEvents::Revived(id, amount) => {
+                self.add(format!("{} gets back up with {}", name(*id), amount), graphics::WHITE);
+            },
+            BattleEvents::StatusApplied(id, kind) => {
+                self.add(format!("{} is affected by {}", name(*id), kind.name()), palette::GREY);
+            },
+            BattleEvents::StatusExpired(id, kind) => {
+                self.add(format!("{}'s {} wears off", name(*id), kind.name()), palette::GREY);
+            }
+        }
+    }
+
+    fn add(&mut self, text: String, color: Color) {
+        self.lines.push(LogLine {
+            text: text,
+            color: color,
+            time: self.time
+        });
+
+        // Keep the same lines in view while scrolled back
+        if self.scroll > 0 {
+            self.scroll(1);
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, project: &Projector) -> GameResult {
+        let end = self.lines.len() - self.scroll.min(self.lines.len());
+        let start = end.saturating_sub(VISIBLE_LINES);
+
+        for (row, line) in self.lines[start..end].iter().enumerate() {
+            let alpha = if self.scroll > 0 {
+                1.0
+            } else {
+                1.0 - ((self.time - line.time - FADE_DELAY) / FADE_DURATION).clamp(0.0, 1.0)
+            };
+
+            if alpha <= 0.0 {
+                continue;
+            }
+
+            let mut text = graphics::Text::new(line.text.clone());
+            text.set_font(self.font, graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE)));
+            graphics::queue_text(
+                ctx,
+                &text,
+                Point2::new(0.0, project.scale(row as f32 * LINE_HEIGHT)),
+                Some(Color::new(line.color.r, line.color.g, line.color.b, alpha))
+            );
+        }
+
+        graphics::draw_queued_text(
+            ctx,
+            (project.origin(),),
+            None,
+            graphics::FilterMode::Linear
+        )?;
+
+        Ok(())
+    }
+}
+
+fn describe_damage<N: Fn(CombatantId) -> String>(report: &DamageReport, name: &N) -> String {
+    let mut description = match report.source {
+        DamageSource::Attack(attacker) =>
+            format!("{} hits {} for {}", name(attacker), name(report.target), report.amount),
+        DamageSource::Status(kind) =>
+            format!("{} takes {} from {}", name(report.target), report.amount, kind.name())
+    };
+
+    let mut modifiers = Vec::new();
+
+    if report.balance_multiplier < 1.0 {
+        modifiers.push(format!("off-balance x{:.1}", report.balance_multiplier));
+    } else if report.balance_multiplier > 1.0 {
+        modifiers.push(format!("balanced x{:.1}", report.balance_multiplier));
+    }
+
+    if report.block_reduction > 0 {
+        modifiers.push(format!("blocked {}", report.block_reduction));
+    }
+
+    if modifiers.len() > 0 {
+        description.push_str(&format!(" ({})", modifiers.join(", ")));
+    }
+
+    description
+}
@@ -0,0 +1,77 @@
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Color, Font};
+
+use crate::projector::{Projector};
+
+const DURATION: f32 = 1.0;
+const RISE_DISTANCE: f32 = 30.0;
+/// Numbers spawned close together are spread out so they don't overlap
+const STAGGER_X: f32 = 14.0;
+const STAGGER_STEPS: usize = 3;
+
+struct DamageNumber {
+    text: String,
+    color: Color,
+    start_time: f32,
+    offset_x: f32
+}
+
+/// Numbers that rise and fade out from where they were spawned.
+pub struct DamageNumbers {
+    time: f32,
+    numbers: Vec<DamageNumber>,
+    spawned: usize
+}
+
+impl DamageNumbers {
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            numbers: Vec::new(),
+            spawned: 0
+        }
+    }
+
+    pub fn update_time(&mut self, current_time: f32) {
+        self.time = current_time;
+
+        let time = self.time;
+        self.numbers.retain(|number| time - number.start_time < DURATION);
+    }
+
+    pub fn spawn<T: Into<String>>(&mut self, text: T, color: Color) {
+        if self.numbers.len() == 0 {
+            self.spawned = 0;
+        }
+
+        self.numbers.push(DamageNumber {
+            text: text.into(),
+            color: color,
+            start_time: self.time,
+            offset_x: (self.spawned % STAGGER_STEPS) as f32 * STAGGER_X
+        });
+
+        self.spawned += 1;
+    }
+
+    pub fn draw(&self, ctx: &mut Context, project: &Projector) -> GameResult {
+
+        for number in self.numbers.iter() {
+            let progress = (self.time - number.start_time) / DURATION;
+            let alpha = 1.0 - ezing::quad_in(progress);
+
+            let mut text = graphics::Text::new(number.text.clone());
+            text.set_font(Font::default(), graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 1.2)));
+            graphics::draw(
+                ctx,
+                &text,
+                (
+                    project.coords(number.offset_x, -RISE_DISTANCE * ezing::quad_out(progress)),
+                    Color::new(number.color.r, number.color.g, number.color.b, alpha)
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+}
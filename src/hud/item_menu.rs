@@ -0,0 +1,79 @@
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Font};
+
+use crate::battle::item::{InventorySlot};
+use crate::palette;
+use crate::projector::{Projector};
+
+pub const MENU_WIDTH: f32 = 200.0;
+const ROW_HEIGHT: f32 = 22.0;
+
+pub fn height(items: &[InventorySlot]) -> f32 {
+    ROW_HEIGHT * items.len().max(1) as f32
+}
+
+/// Row under a point relative to the menu's projector.
+pub fn row_at(x: f32, y: f32, project: &Projector, items: &[InventorySlot]) -> Option<usize> {
+    if x < 0.0 || x > project.scale(MENU_WIDTH) || y < 0.0 {
+        return None;
+    }
+
+    let row = (y / project.scale(ROW_HEIGHT)) as usize;
+
+    if row < items.len() {
+        Some(row)
+    } else {
+        None
+    }
+}
+
+pub fn draw(
+    ctx: &mut Context,
+    project: &Projector,
+    items: &[InventorySlot],
+    hovered: Option<usize>
+) -> GameResult {
+    let border = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::stroke(1.0),
+        graphics::Rect {
+            x: 0.0,
+            y: 0.0,
+            w: project.scale(MENU_WIDTH),
+            h: project.scale(height(items))
+        },
+        graphics::WHITE
+    )?;
+    graphics::draw(ctx, &border, (project.origin(),))?;
+
+    if items.is_empty() {
+        let mut text = graphics::Text::new("No items");
+        text.set_font(Font::default(), graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE)));
+        graphics::draw(ctx, &text, (project.coords(6.0, 4.0), palette::GREY))?;
+    }
+
+    for (i, slot) in items.iter().enumerate() {
+        if hovered == Some(i) {
+            let highlight = graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::fill(),
+                graphics::Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    w: project.scale(MENU_WIDTH),
+                    h: project.scale(ROW_HEIGHT)
+                },
+                palette::darker(palette::GREY)
+            )?;
+            graphics::draw(ctx, &highlight, (project.coords(0.0, i as f32 * ROW_HEIGHT),))?;
+        }
+
+        let color = if slot.count > 0 { graphics::WHITE } else { palette::GREY };
+
+        let mut text = graphics::Text::new(format!("{} x{}", slot.item.name, slot.count));
+        text.set_font(Font::default(), graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE)));
+        graphics::draw(ctx, &text, (project.coords(6.0, i as f32 * ROW_HEIGHT + 4.0), color))?;
+    }
+
+    Ok(())
+}
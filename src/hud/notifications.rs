@@ -0,0 +1,52 @@
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Font};
+
+use crate::palette;
+use crate::projector::{Projector};
+
+const DISPLAY_DURATION: f32 = 4.0;
+const LINE_HEIGHT: f32 = 30.0;
+
+/// Messages shown across the top of the screen for a few seconds, outside
+/// of any battle.
+pub struct Notifications {
+    font: Font,
+    time: f32,
+    messages: Vec<(String, f32)>
+}
+
+impl Notifications {
+    pub fn new(font: Font) -> Self {
+        Self {
+            font: font,
+            time: 0.0,
+            messages: Vec::new()
+        }
+    }
+
+    pub fn push<T: Into<String>>(&mut self, text: T) {
+        self.messages.push((text.into(), self.time + DISPLAY_DURATION));
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+
+        let time = self.time;
+        self.messages.retain(|(_, expiry)| *expiry > time);
+    }
+
+    pub fn draw(&self, ctx: &mut Context, project: &Projector) -> GameResult {
+        let mut offset = 0.0;
+
+        for (message, _) in self.messages.iter() {
+            let mut text = graphics::Text::new(message.clone());
+            text.set_font(self.font, graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 1.5)));
+
+            let line_projector = project.centered_horizontal(text.width(ctx) as f32 / project.scale(1.0));
+            graphics::draw(ctx, &text, (line_projector.coords(0.0, offset), palette::YELLOW))?;
+            offset += LINE_HEIGHT;
+        }
+
+        Ok(())
+    }
+}
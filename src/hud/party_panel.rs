@@ -0,0 +1,46 @@
+use ggez::{Context, GameResult};
+use ggez::graphics::{self, Font};
+
+use crate::definitions::{LevelDefinition};
+use crate::palette;
+use crate::party::{PartyMember};
+use crate::projector::{Projector};
+
+const LINE_HEIGHT: f32 = 24.0;
+
+/// Lists the party in formation order while exploring, numbered by the key
+/// that selects them for swapping places.
+pub fn draw(
+    ctx: &mut Context,
+    project: &Projector,
+    font: Font,
+    party: &[PartyMember],
+    levels: &[LevelDefinition],
+    selected: Option<usize>
+) -> GameResult {
+    let panel_projector = project.bottom_left(LINE_HEIGHT * party.len() as f32);
+
+    for (i, member) in party.iter().enumerate() {
+        let condition = if member.is_standing() {
+            format!("{}/{}", member.fatigue, member.max_fatigue(levels))
+        } else {
+            "KO".to_string()
+        };
+
+        let color = if selected == Some(i) {
+            palette::YELLOW
+        } else if member.is_standing() {
+            graphics::WHITE
+        } else {
+            palette::GREY
+        };
+
+        let mut text = graphics::Text::new(
+            format!("{} {}  Lv {}  {}", i + 1, member.definition.name, member.level, condition)
+        );
+        text.set_font(font, graphics::Scale::uniform(project.scale(graphics::DEFAULT_FONT_SCALE * 1.2)));
+        graphics::draw(ctx, &text, (panel_projector.coords(0.0, i as f32 * LINE_HEIGHT), color))?;
+    }
+
+    Ok(())
+}
@@ -1,8 +1,11 @@
 pub mod battle;
+pub mod definitions;
 pub mod explore;
 pub mod fps_meter;
 pub mod hud;
 pub mod input;
 pub mod palette;
+pub mod party;
 pub mod projector;
+pub mod settings;
 pub mod ui;
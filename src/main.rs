@@ -5,13 +5,26 @@ use ggez::graphics;
 use ggez::input::mouse::{MouseButton};
 use ggez::timer;
 use nalgebra::{Point2};
+use rand::{random, Rng, SeedableRng};
+use rand::rngs::{StdRng};
 use std::path::{PathBuf};
 use std::env;
+use std::io;
 
-use rpg_battle::battle::{BattleState, BattleEvents};
+use rpg_battle::battle::{BattleState, BattleEvents, Outcome, PlayerAction};
+use rpg_battle::battle::combatant::{CombatantId};
+use rpg_battle::battle::item::{Inventory};
+use rpg_battle::battle::replay::{Replay, ReplayPlayer};
+use rpg_battle::definitions::{self, Definitions, PlayerDefinition};
 use rpg_battle::explore::{ExploreState, ExploreEvents};
 use rpg_battle::fps_meter::{FpsMeter};
+use rpg_battle::hud::battle_hud::{BattleHud};
+use rpg_battle::hud::character_screen;
+use rpg_battle::hud::notifications::{Notifications};
+use rpg_battle::hud::party_panel;
 use rpg_battle::input::{MoveState};
+use rpg_battle::party::{PartyMember};
+use rpg_battle::settings::{self, Settings, BATTLE_SPEEDS, CLOCK_MODES, UI_SCALES};
 use rpg_battle::ui::options::{Options};
 use rpg_battle::projector::{Projector};
 
@@ -19,24 +32,68 @@ const SCREEN_WIDTH: f32 = 1440.0;
 const SCREEN_HEIGHT: f32 = 900.0;
 const DESIRED_FPS: u32 = 60;
 const RANDOMISE_INTERVAL: f32 = 2.0;
+const REPLAY_DIRECTORY: &str = "replays";
+const SETTINGS_PATH: &str = "settings.json";
+const SETTINGS_ROW_HEIGHT: f32 = 30.0;
 
 // TODO make important state changes wait for animation (eg end battle)
 // TODO consider remaining_update_time delta in the draw step
 // TODO split battle module into more parts
 // TODO revise privacy settings for structs and members
 struct MainState {
+    rng: StdRng,
     fps_meter: FpsMeter,
     font: graphics::Font,
+    definitions: Definitions,
+    /// In formation order, front first
+    party: Vec<PartyMember>,
+    /// Member picked to swap places with the next one picked
+    formation_selection: Option<usize>,
+    inventory: Inventory,
+    notifications: Notifications,
     randomise_timer: f32,
-    battle: Option<BattleState>,
+    battle: Option<Battle>,
+    replay_player: Option<ReplayPlayer>,
     events: Vec<MainEvents>,
-    ui_scale: f32,
+    settings: Settings,
     ui_scale_input: Options,
+    battle_speed_input: Options,
+    clock_mode_input: Options,
     display_settings: bool,
+    display_character_screen: bool,
     explore: ExploreState,
     move_state: MoveState
 }
 
+struct Battle {
+    state: BattleState,
+    hud: BattleHud
+}
+
+impl Battle {
+    fn new(state: BattleState, font: graphics::Font) -> Self {
+        Self {
+            hud: BattleHud::new(&state, font),
+            state: state
+        }
+    }
+
+    /// Queues the slot's action for a selected player, or uses it on the
+    /// pending player's turn.
+    fn use_hotbar(&mut self, slot: usize, main_events: &mut Vec<MainEvents>) {
+        match self.hud.selected_player() {
+            Some(i) => {
+                let action = self.state.hotbar_action(i, slot);
+                self.state.queue_action(i, action);
+                self.hud.clear_player_selection();
+            },
+            None if self.state.player_move_pending() => {
+                self.state.player_use_hotbar(slot, battle_event_notifier(main_events));
+            },
+            None => {}
+        }
+    }
+}
 
 enum MainEvents {
     BattleEvent(BattleEvents),
@@ -52,7 +109,7 @@ fn explore_event_notifier<'a>(main_events: &'a mut Vec<MainEvents>) -> impl 'a +
 }
 
 impl MainState {
-    fn new(ctx: &mut ggez::Context) -> ggez::GameResult<MainState> {
+    fn new(ctx: &mut ggez::Context, seed: u64, replay: Option<Replay>) -> ggez::GameResult<MainState> {
 
         let maybe_font = graphics::Font::new_glyph_font_bytes(
             ctx,
@@ -63,17 +120,54 @@ impl MainState {
             Err(e) => panic!("{}", e)
         };
 
+        let definitions = match Definitions::load(definitions::default_path()) {
+            Ok(result) => result,
+            Err(e) => panic!("{}", e)
+        };
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let explore_seed = rng.gen();
+
+        let battle = replay.as_ref().map(|replay| Battle::new(replay.start(), font));
+        let replay_player = replay.map(ReplayPlayer::new);
+
+        let settings = load_settings();
+        let defaults = Settings::default();
+
+        let party = definitions.party.iter()
+            .map(|definition| PartyMember::new(definition, &definitions))
+            .collect();
+
         let s = MainState {
+            rng: rng,
             fps_meter: FpsMeter::new(),
             font: font,
+            party: party,
+            formation_selection: None,
+            inventory: definitions.starting_inventory(),
+            notifications: Notifications::new(font),
             randomise_timer: 0.0,
-            battle: None,
+            battle: battle,
+            replay_player: replay_player,
             events: Vec::new(),
-            ui_scale: 1.0,
-            ui_scale_input: Options::new(5, 2),
+            ui_scale_input: Options::new(
+                UI_SCALES.len() as i16,
+                settings::option_index(&UI_SCALES, settings.ui_scale, defaults.ui_scale)
+            ),
+            battle_speed_input: Options::new(
+                BATTLE_SPEEDS.len() as i16,
+                settings::option_index(&BATTLE_SPEEDS, settings.battle_speed, defaults.battle_speed)
+            ),
+            clock_mode_input: Options::new(
+                CLOCK_MODES.len() as i16,
+                settings::option_index(&CLOCK_MODES, settings.clock_mode, defaults.clock_mode)
+            ),
+            settings: settings,
             display_settings: false,
-            explore: ExploreState::new(ctx, SCREEN_WIDTH, SCREEN_HEIGHT)?,
-            move_state: Default::default()
+            display_character_screen: false,
+            explore: ExploreState::new(ctx, SCREEN_WIDTH, SCREEN_HEIGHT, definitions.enemies.len(), explore_seed)?,
+            move_state: Default::default(),
+            definitions: definitions
         };
 
         Ok(s)
@@ -86,31 +180,117 @@ impl MainState {
             let event = main_events.remove(0);
 
             match event {
-                MainEvents::BattleEvent(BattleEvents::End(victory)) => {
-                    self.battle = None;
+                MainEvents::BattleEvent(BattleEvents::End(outcome)) => {
+                    let battle = self.battle.take();
 
-                    if victory {
-                        self.explore.notify_battle_end();
-                    } else {
-                        self.explore.notify_player_defeat();
+                    // A replayed battle has no monsters of its own in the current scene
+                    if self.replay_player.take().is_some() {
+                        self.notifications.push(format!("Replay finished: {:?}", outcome));
+                        continue;
+                    }
+
+                    if let Some(battle) = battle {
+                        save_replay(battle.state.replay());
+
+                        for (i, member) in self.party.iter_mut().enumerate() {
+                            member.fatigue = battle.state.players()[i].combatant.current_resource;
+                        }
+
+                        self.inventory = battle.state.inventory().clone();
+                        self.inventory.remove_empty();
+
+                        if outcome == Outcome::Victory {
+                            for enemy in battle.state.enemies() {
+                                for drop in enemy.drops() {
+                                    if self.rng.gen::<f32>() < drop.chance {
+                                        if let Some(item) = self.definitions.item(&drop.item) {
+                                            self.inventory.add(item, 1);
+                                            self.notifications.push(format!("Found a {}", item.name));
+                                        }
+                                    }
+                                }
+                            }
+
+                            let experience = battle.state.experience_earned();
+
+                            for (i, member) in self.party.iter_mut().enumerate() {
+                                // Members knocked out at the end miss out
+                                if !battle.state.players()[i].combatant.is_standing() {
+                                    continue;
+                                }
+
+                                if member.gain_experience(experience, &self.definitions.levels) > 0 {
+                                    self.notifications.push(
+                                        format!("{} reached level {}", member.definition.name, member.level)
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    match outcome {
+                        Outcome::Victory => self.explore.notify_battle_end(),
+                        Outcome::Defeat => self.explore.notify_player_defeat(),
+                        Outcome::Escaped => {
+                            self.explore.notify_escape();
+                            self.notifications.push("The party escaped");
+                        }
+                    }
+
+                    // A party that can't fight is carried back to rest, as after a defeat
+                    if !self.party.iter().any(PartyMember::is_standing) {
+                        for member in self.party.iter_mut() {
+                            member.rest(&self.definitions.levels);
+                        }
+
+                        self.notifications.push("The party was carried back to rest");
                     }
-                },
-                MainEvents::BattleEvent(BattleEvents::EnemyDown(id)) => {
-                    self.explore.notify_monster_down(id);
                 },
                 MainEvents::BattleEvent(e) => {
                     match &mut self.battle {
-                        Some(battle) => battle.handle_event(&e, battle_event_notifier(main_events)),
+                        Some(battle) => {
+                            battle.hud.handle_event(&e, &battle.state);
+
+                            if let BattleEvents::Down(CombatantId::Enemy(i)) = e {
+                                if self.replay_player.is_none() {
+                                    self.explore.notify_monster_down(battle.state.enemies()[i].world_id);
+                                }
+                            }
+                        },
                         None => {}
                     }
                 },
-                MainEvents::ExploreEvent(ExploreEvents::MonsterEncounter(id)) => {
+                MainEvents::ExploreEvent(ExploreEvents::MonsterEncounter(id, kind)) => {
+                    let enemy = &self.definitions.enemies[kind];
+
                     match &mut self.battle {
                         Some(battle) => {
-                            battle.add_enemy(id);
+                            battle.state.add_enemy(id, enemy, battle_event_notifier(main_events));
                         },
                         None => {
-                            self.battle = Some(BattleState::new(id));
+                            let battle_seed = self.rng.gen();
+
+                            self.formation_selection = None;
+                            self.display_character_screen = false;
+
+                            let levels = &self.definitions.levels;
+                            let party: Vec<PlayerDefinition> = self.party.iter()
+                                .map(|member| member.battle_definition(levels))
+                                .collect();
+                            let party_fatigue: Vec<i32> = self.party.iter()
+                                .map(|member| member.fatigue)
+                                .collect();
+
+                            let mut state = BattleState::new(battle_seed, &party, &party_fatigue, &self.inventory, id, enemy);
+                            state.set_clock_mode(self.settings.clock_mode);
+
+                            self.battle = Some(Battle::new(state, self.font));
+                        }
+                    }
+
+                    if let Some(battle) = &self.battle {
+                        if let Some(enemy) = battle.state.enemies().last() {
+                            self.explore.notify_monster_marker(id, enemy.marker, graphics::Color::from(enemy.combatant.color));
                         }
                     }
                 }
@@ -147,48 +327,113 @@ impl event::EventHandler for MainState {
     fn text_input_event(&mut self, _ctx: &mut ggez::Context, character: char) {
 
         match &mut self.battle {
-            Some(battle) => {
-                if character == '1' && battle.player_move_pending() {
-                    battle.player_attack_move(battle_event_notifier(&mut self.events));
+            Some(battle) if self.replay_player.is_none() => {
+                match character.to_digit(10) {
+                    // Keys 1-9 are the first nine slots and 0 is the tenth
+                    Some(digit) => {
+                        battle.use_hotbar((digit as usize + 9) % 10, &mut self.events);
+                    },
+                    None => {}
+                }
+
+                if character == 'i' {
+                    battle.hud.toggle_item_menu();
                 }
 
-                if character == '2' && battle.player_move_pending() {
-                    battle.player_block_move(battle_event_notifier(&mut self.events));
+                if character == 'x' {
+                    if let Some(i) = battle.hud.selected_player() {
+                        battle.state.cancel_queued_action(i);
+                        battle.hud.clear_player_selection();
+                    }
+                }
+
+                // A selected player is being given a queued action, escaping isn't one
+                if character == 'e' && battle.state.player_move_pending() && battle.hud.selected_player().is_none() {
+                    battle.state.player_escape(battle_event_notifier(&mut self.events));
                 }
             },
-            None => {}
+            None => {
+                match character.to_digit(10) {
+                    // Picking two members by number swaps their places in the formation
+                    Some(digit) if digit >= 1 && digit as usize <= self.party.len() => {
+                        let i = digit as usize - 1;
+
+                        match self.formation_selection.take() {
+                            Some(j) => self.party.swap(i, j),
+                            None => self.formation_selection = Some(i)
+                        }
+                    },
+                    _ => {}
+                }
+            },
+            _ => {}
         }
 
         if character == 'h' {
             self.display_settings = !self.display_settings;
         }
 
+        if character == 'c' && self.battle.is_none() {
+            self.display_character_screen = !self.display_character_screen;
+        }
+
+        if character == 'r' && self.battle.is_none() {
+            for member in self.party.iter_mut() {
+                member.rest(&self.definitions.levels);
+            }
+
+            self.explore.notify_party_rest();
+            self.notifications.push("The party rests");
+        }
+
         self.flush_events();
     }
 
     fn mouse_button_down_event(
         &mut self, _ctx: &mut ggez::Context, _button: MouseButton, x: f32, y: f32
     ) {
-        // Select hovered enemy
+        // Select hovered enemy or player, or use hovered hotbar slot or item
         match &mut self.battle {
-            Some(battle) => {
-                if let Some(i) = battle.hovered_enemy {
-                    battle.target_enemy = i;
+            Some(battle) if self.replay_player.is_none() => {
+                if let Some(slot) = battle.hud.hovered_hotbar_slot {
+                    battle.use_hotbar(slot, &mut self.events);
+                }
+
+                if let Some(i) = battle.hud.hovered_enemy {
+                    battle.state.set_target_enemy(i);
+                }
+
+                if let Some(i) = battle.hud.hovered_player {
+                    battle.hud.toggle_player_selection(i, &battle.state);
+                }
+
+                if let Some(index) = battle.hud.hovered_item {
+                    match battle.hud.selected_player() {
+                        Some(i) => {
+                            battle.state.queue_action(i, PlayerAction::Item(index));
+                            battle.hud.clear_player_selection();
+                        },
+                        None => battle.state.player_use_item(index, battle_event_notifier(&mut self.events))
+                    }
                 }
             },
-            None => {}
+            _ => {}
         }
 
-        // Update UI scale input
+        self.flush_events();
+
+        // Update settings inputs
         if self.display_settings {
             let projector = Projector::new(
                 Point2::new(0.0, 0.0),
-                self.ui_scale,
+                self.settings.ui_scale,
                 SCREEN_WIDTH,
                 SCREEN_HEIGHT
             ).margins(90.0, 20.0);
 
             let settings_projector = projector.centered(300.0, 20.0);
+            let previous_settings = self.settings;
+
             let ui_scale_input_projector = settings_projector.top_right(170.0);
             let input_value = self.ui_scale_input.handle_mouse_down(
                 ui_scale_input_projector.to_local_x(x),
@@ -196,13 +441,36 @@ impl event::EventHandler for MainState {
                 &ui_scale_input_projector
             );
 
-            self.ui_scale = match input_value {
-                0 => 0.7,
-                1 => 0.9,
-                3 => 1.2,
-                4 => 1.5,
-                _ => 1.0
-            };
+            self.settings.ui_scale = UI_SCALES[input_value as usize];
+
+            let battle_speed_input_projector = settings_projector.offset_down(SETTINGS_ROW_HEIGHT).top_right(170.0);
+            let input_value = self.battle_speed_input.handle_mouse_down(
+                battle_speed_input_projector.to_local_x(x),
+                battle_speed_input_projector.to_local_y(y),
+                &battle_speed_input_projector
+            );
+
+            self.settings.battle_speed = BATTLE_SPEEDS[input_value as usize];
+
+            let clock_mode_input_projector = settings_projector.offset_down(SETTINGS_ROW_HEIGHT * 2.0).top_right(90.0);
+            let input_value = self.clock_mode_input.handle_mouse_down(
+                clock_mode_input_projector.to_local_x(x),
+                clock_mode_input_projector.to_local_y(y),
+                &clock_mode_input_projector
+            );
+
+            self.settings.clock_mode = CLOCK_MODES[input_value as usize];
+
+            match &mut self.battle {
+                Some(battle) if self.replay_player.is_none() => {
+                    battle.state.set_clock_mode(self.settings.clock_mode);
+                },
+                _ => {}
+            }
+
+            if self.settings != previous_settings {
+                save_settings(&self.settings);
+            }
         }
     }
 
@@ -216,20 +484,29 @@ impl event::EventHandler for MainState {
     ) {
         let projector = Projector::new(
             Point2::new(0.0, 0.0),
-            self.ui_scale,
+            self.settings.ui_scale,
             SCREEN_WIDTH,
             SCREEN_HEIGHT
         ).margins(90.0, 20.0);
 
         match &mut self.battle {
             Some(battle) => {
-                battle.handle_mouse_move(x, y, &projector);
+                battle.hud.handle_mouse_move(x, y, &projector, &battle.state);
             },
             None => {}
         }
 
     }
 
+    fn mouse_wheel_event(&mut self, _ctx: &mut ggez::Context, _x: f32, y: f32) {
+        match &mut self.battle {
+            Some(battle) => {
+                battle.hud.handle_mouse_wheel(y);
+            },
+            None => {}
+        }
+    }
+
     fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
 
         self.fps_meter.update_start(ctx);
@@ -238,14 +515,35 @@ impl event::EventHandler for MainState {
             self.fps_meter.update_loop(ctx);
 
             let delta = 1.0 / (DESIRED_FPS as f32);
+            let battle_delta = delta * self.settings.battle_speed;
 
             self.randomise_timer += delta;
+            self.notifications.update(delta);
 
-            self.explore.update(self.move_state.get_move(), delta, explore_event_notifier(&mut self.events));
+            match &mut self.replay_player {
+                Some(replay_player) => {
+                    if let Some(battle) = &mut self.battle {
+                        replay_player.step(&mut battle.state, battle_event_notifier(&mut self.events));
+                    }
+
+                    if replay_player.is_finished() && self.events.is_empty() {
+                        self.notifications.push("Replay finished without an outcome");
+                        self.replay_player = None;
+                        self.battle = None;
+                    }
+                },
+                None => {
+                    self.explore.update(self.move_state.get_move(), delta, explore_event_notifier(&mut self.events));
+
+                    if let Some(battle) = &mut self.battle {
+                        battle.state.tick(battle_delta, battle_event_notifier(&mut self.events));
+                    }
+                }
+            }
 
             match &mut self.battle {
                 Some(battle) => {
-                    battle.tick(delta, battle_event_notifier(&mut self.events));
+                    battle.hud.update(&battle.state, battle_delta);
                 },
                 None => {}
             }
@@ -271,25 +569,54 @@ impl event::EventHandler for MainState {
 
         let projector = Projector::new(
             Point2::new(0.0, 0.0),
-            self.ui_scale,
+            self.settings.ui_scale,
             SCREEN_WIDTH,
             SCREEN_HEIGHT
         ).margins(90.0, 20.0);
 
         match &mut self.battle {
             Some(battle) => {
-                battle.draw(ctx, &projector)?;
+                battle.hud.draw(ctx, &projector, &battle.state)?;
             },
-            None => {}
+            None => {
+                party_panel::draw(
+                    ctx,
+                    &projector,
+                    self.font,
+                    &self.party,
+                    &self.definitions.levels,
+                    self.formation_selection
+                )?;
+
+                if self.display_character_screen {
+                    character_screen::draw(ctx, &projector, self.font, &self.party, &self.definitions.levels)?;
+                }
+            }
         }
 
+        self.notifications.draw(ctx, &projector)?;
+
         if self.display_settings {
             let settings_projector = projector.centered(300.0, 20.0);
-            let mut ui_scale_text = graphics::Text::new(format!("Scale {}", self.ui_scale));
+            let mut ui_scale_text = graphics::Text::new(format!("Scale {}", self.settings.ui_scale));
             ui_scale_text.set_font(self.font, graphics::Scale::uniform(settings_projector.scale(graphics::DEFAULT_FONT_SCALE * 2.0)));
 
             graphics::draw(ctx, &ui_scale_text, (settings_projector.origin(),))?;
             self.ui_scale_input.draw(ctx, &settings_projector.top_right(170.0))?;
+
+            let battle_speed_projector = settings_projector.offset_down(SETTINGS_ROW_HEIGHT);
+            let mut battle_speed_text = graphics::Text::new(format!("Speed {}x", self.settings.battle_speed));
+            battle_speed_text.set_font(self.font, graphics::Scale::uniform(battle_speed_projector.scale(graphics::DEFAULT_FONT_SCALE * 2.0)));
+
+            graphics::draw(ctx, &battle_speed_text, (battle_speed_projector.origin(),))?;
+            self.battle_speed_input.draw(ctx, &battle_speed_projector.top_right(170.0))?;
+
+            let clock_mode_projector = settings_projector.offset_down(SETTINGS_ROW_HEIGHT * 2.0);
+            let mut clock_mode_text = graphics::Text::new(format!("Mode {:?}", self.settings.clock_mode));
+            clock_mode_text.set_font(self.font, graphics::Scale::uniform(clock_mode_projector.scale(graphics::DEFAULT_FONT_SCALE * 2.0)));
+
+            graphics::draw(ctx, &clock_mode_text, (clock_mode_projector.origin(),))?;
+            self.clock_mode_input.draw(ctx, &clock_mode_projector.top_right(90.0))?;
         }
 
         self.fps_meter.draw(ctx)?;
@@ -300,8 +627,51 @@ impl event::EventHandler for MainState {
     }
 }
 
+fn save_replay(replay: &Replay) {
+    let mut path = PathBuf::from(REPLAY_DIRECTORY);
+    path.push(format!("{}.json", replay.seed));
+
+    if let Err(e) = replay.save(&path) {
+        eprintln!("Failed to save replay to {}: {}", path.display(), e);
+    }
+}
+
+fn load_settings() -> Settings {
+    match Settings::load(SETTINGS_PATH) {
+        Ok(settings) => settings,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                eprintln!("Failed to load settings from {}: {}", SETTINGS_PATH, e);
+            }
+
+            Settings::default()
+        }
+    }
+}
+
+fn save_settings(settings: &Settings) {
+    if let Err(e) = settings.save(SETTINGS_PATH) {
+        eprintln!("Failed to save settings to {}: {}", SETTINGS_PATH, e);
+    }
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = env::args().skip_while(|arg| arg != name).skip(1);
+
+    args.next()
+}
+
 pub fn main() -> ggez::GameResult {
 
+    let seed = arg_value("--seed")
+        .map(|seed| seed.parse().expect("--seed must be a whole number"))
+        .unwrap_or_else(random);
+
+    let replay = arg_value("--replay").map(|path| match Replay::load(&path) {
+        Ok(result) => result,
+        Err(e) => panic!("Failed to load replay {}: {}", path, e)
+    });
+
     // Make a Context.
     let mut cb = ContextBuilder::new("dancras/rpg_battle", "dancras");
 
@@ -320,6 +690,6 @@ pub fn main() -> ggez::GameResult {
         .build()
         .expect("Failed to build ggez context");
 
-    let state = &mut MainState::new(ctx)?;
+    let state = &mut MainState::new(ctx, seed, replay)?;
     event::run(ctx, event_loop, state)
 }
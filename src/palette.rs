@@ -3,6 +3,7 @@ use ggez::graphics::Color;
 pub const BLUE: Color = Color::new(0.6, 0.6, 1.0, 1.0);
 pub const GREEN: Color = Color::new(0.2, 1.0, 0.4, 1.0);
 pub const GREY: Color = Color::new(0.6, 0.6, 0.6, 1.0);
+pub const PURPLE: Color = Color::new(0.7, 0.3, 0.9, 1.0);
 pub const RED: Color = Color::new(1.0, 0.2, 0.3, 1.0);
 pub const YELLOW: Color = Color::new(1.0, 1.0, 0.3, 1.0);
 
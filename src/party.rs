@@ -0,0 +1,288 @@
+use std::iter::Sum;
+
+use crate::battle::ability::{AbilityEffect};
+use crate::definitions::{Definitions, EquipmentDefinition, LevelDefinition, PlayerDefinition};
+
+pub const MAX_PARTY_SIZE: usize = 4;
+
+/// Equipment can't make an attack any quicker than this.
+const MIN_ATTACK_ACTION_TIME: f32 = 50.0;
+
+/// A party member's progress and condition, kept between battles.
+pub struct PartyMember {
+    pub definition: PlayerDefinition,
+    /// Starting from 1
+    pub level: usize,
+    pub experience: u32,
+    /// Left over from the last battle, 0 means knocked out
+    pub fatigue: i32,
+    /// At most one for each slot
+    pub equipment: Vec<EquipmentDefinition>
+}
+
+impl PartyMember {
+    pub fn new(definition: &PlayerDefinition, definitions: &Definitions) -> Self {
+        Self {
+            definition: definition.clone(),
+            level: 1,
+            experience: 0,
+            fatigue: definition.max_fatigue,
+            equipment: definition.equipment.iter()
+                .filter_map(|name| definitions.equipment(name))
+                .cloned()
+                .collect()
+        }
+    }
+
+    pub fn is_standing(&self) -> bool {
+        self.fatigue > 0
+    }
+
+    pub fn max_fatigue(&self, levels: &[LevelDefinition]) -> i32 {
+        self.definition.max_fatigue + levels[self.level - 1].max_fatigue
+    }
+
+    /// Recovers all fatigue, getting a knocked out member back up.
+    pub fn rest(&mut self, levels: &[LevelDefinition]) {
+        self.fatigue = self.max_fatigue(levels);
+    }
+
+    /// Adds experience and returns how many levels were gained.
+    pub fn gain_experience(&mut self, amount: u32, levels: &[LevelDefinition]) -> usize {
+        let previous_level = self.level;
+        self.experience += amount;
+
+        // levels[self.level] is the level after the current one
+        while self.level < levels.len() && self.experience >= levels[self.level].experience {
+            self.level += 1;
+        }
+
+        self.level - previous_level
+    }
+
+    /// One modifier added up over everything equipped.
+    pub fn equipment_total<T: Sum, F: Fn(&EquipmentDefinition) -> T>(&self, modifier: F) -> T {
+        self.equipment.iter().map(modifier).sum()
+    }
+
+    /// The definition with this member's level and equipment applied, for
+    /// starting a battle.
+    pub fn battle_definition(&self, levels: &[LevelDefinition]) -> PlayerDefinition {
+        let level = &levels[self.level - 1];
+        let mut definition = self.definition.clone();
+
+        let damage = self.equipment_total(|equipment| equipment.damage);
+        let attack_action_time = self.equipment_total(|equipment| equipment.attack_action_time);
+        let block_fatigue_cost = self.equipment_total(|equipment| equipment.block_fatigue_cost);
+
+        definition.max_fatigue = self.max_fatigue(levels);
+        definition.block_damage_divisor =
+            (definition.block_damage_divisor + self.equipment_total(|equipment| equipment.block_damage_divisor)).max(1);
+        definition.balance_recovery =
+            (definition.balance_recovery + self.equipment_total(|equipment| equipment.balance_recovery)).min(1.0);
+
+        for ability in definition.abilities.iter_mut() {
+            if ability.has_damage() {
+                let shortest = ability.action_time.min(MIN_ATTACK_ACTION_TIME);
+                ability.action_time = (ability.action_time + attack_action_time).max(shortest);
+            }
+
+            if ability.has_block() {
+                ability.fatigue_cost = (ability.fatigue_cost + block_fatigue_cost).max(0);
+            }
+
+            ability.action_time *= level.action_time;
+
+            for effect in ability.effects.iter_mut() {
+                if let AbilityEffect::Damage { power } = effect {
+                    *power = ((*power + damage).max(0) as f32 * level.damage).round() as i32;
+                }
+            }
+        }
+
+        definition
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::ability::{AbilityDefinition, TargetKind};
+    use crate::battle::combatant::{BLOCK_DAMAGE_DIVISOR};
+    use crate::definitions::{EquipmentSlot};
+
+    fn level(experience: u32, max_fatigue: i32, damage: f32, action_time: f32) -> LevelDefinition {
+        LevelDefinition {
+            experience: experience,
+            max_fatigue: max_fatigue,
+            damage: damage,
+            action_time: action_time
+        }
+    }
+
+    fn levels() -> Vec<LevelDefinition> {
+        vec![
+            level(0, 0, 1.0, 1.0),
+            level(100, 10, 1.5, 0.5),
+            level(250, 20, 2.0, 0.5),
+            level(500, 30, 2.0, 0.5)
+        ]
+    }
+
+    fn equipment(slot: EquipmentSlot) -> EquipmentDefinition {
+        EquipmentDefinition {
+            name: slot.name().to_string(),
+            slot: slot,
+            damage: 0,
+            block_damage_divisor: 0,
+            attack_action_time: 0.0,
+            block_fatigue_cost: 0,
+            balance_recovery: 0.0
+        }
+    }
+
+    fn member(equipment: Vec<EquipmentDefinition>) -> PartyMember {
+        let definition = PlayerDefinition {
+            name: "Member".to_string(),
+            color: [1.0, 1.0, 1.0, 1.0],
+            max_fatigue: 100,
+            first_action: 10.0,
+            block_hit_time_penalty: 50.0,
+            abilities: vec![
+                AbilityDefinition {
+                    name: "Attack".to_string(),
+                    fatigue_cost: 5,
+                    action_time: 100.0,
+                    target: TargetKind::Enemy,
+                    effects: vec![AbilityEffect::Damage { power: 10 }]
+                },
+                AbilityDefinition {
+                    name: "Block".to_string(),
+                    fatigue_cost: 5,
+                    action_time: 100.0,
+                    target: TargetKind::Caster,
+                    effects: vec![AbilityEffect::Block]
+                }
+            ],
+            on_hit: None,
+            block_damage_divisor: BLOCK_DAMAGE_DIVISOR,
+            balance_recovery: 0.0,
+            equipment: Vec::new()
+        };
+
+        PartyMember {
+            fatigue: definition.max_fatigue,
+            definition: definition,
+            level: 1,
+            experience: 0,
+            equipment: equipment
+        }
+    }
+
+    fn attack_power(definition: &PlayerDefinition) -> i32 {
+        match definition.abilities[0].effects[0] {
+            AbilityEffect::Damage { power } => power,
+            _ => panic!("Attack should be the first ability")
+        }
+    }
+
+    #[test]
+    fn levels_up_on_reaching_the_threshold() {
+        let mut member = member(Vec::new());
+
+        assert_eq!(member.gain_experience(99, &levels()), 0);
+        assert_eq!(member.level, 1);
+
+        assert_eq!(member.gain_experience(1, &levels()), 1);
+        assert_eq!(member.level, 2);
+    }
+
+    #[test]
+    fn one_award_can_gain_several_levels() {
+        let mut member = member(Vec::new());
+
+        assert_eq!(member.gain_experience(300, &levels()), 2);
+        assert_eq!(member.level, 3);
+    }
+
+    #[test]
+    fn levels_stop_at_the_last_defined() {
+        let mut member = member(Vec::new());
+
+        assert_eq!(member.gain_experience(10000, &levels()), 3);
+        assert_eq!(member.level, 4);
+        assert_eq!(member.experience, 10000);
+    }
+
+    #[test]
+    fn level_grows_fatigue_damage_and_speed() {
+        let mut member = member(Vec::new());
+        member.gain_experience(100, &levels());
+
+        let definition = member.battle_definition(&levels());
+
+        assert_eq!(definition.max_fatigue, 110);
+        assert_eq!(attack_power(&definition), 15);
+        assert_eq!(definition.abilities[0].action_time, 50.0);
+    }
+
+    #[test]
+    fn equipment_modifiers_stack() {
+        let mut weapon = equipment(EquipmentSlot::Weapon);
+        weapon.damage = 2;
+        weapon.block_damage_divisor = 1;
+        let mut accessory = equipment(EquipmentSlot::Accessory);
+        accessory.damage = 3;
+        accessory.block_damage_divisor = 2;
+
+        let definition = member(vec![weapon, accessory]).battle_definition(&levels());
+
+        assert_eq!(attack_power(&definition), 15);
+        assert_eq!(definition.block_damage_divisor, BLOCK_DAMAGE_DIVISOR + 3);
+    }
+
+    #[test]
+    fn block_damage_divisor_never_drops_below_1() {
+        let mut armour = equipment(EquipmentSlot::Armour);
+        armour.block_damage_divisor = -10;
+
+        let definition = member(vec![armour]).battle_definition(&levels());
+
+        assert_eq!(definition.block_damage_divisor, 1);
+    }
+
+    #[test]
+    fn equipment_cant_make_attacks_quicker_than_the_minimum() {
+        let mut weapon = equipment(EquipmentSlot::Weapon);
+        weapon.attack_action_time = -80.0;
+
+        let definition = member(vec![weapon]).battle_definition(&levels());
+
+        assert_eq!(definition.abilities[0].action_time, MIN_ATTACK_ACTION_TIME);
+        // Only damaging abilities are affected
+        assert_eq!(definition.abilities[1].action_time, 100.0);
+    }
+
+    #[test]
+    fn attacks_already_quicker_than_the_minimum_are_kept() {
+        let mut weapon = equipment(EquipmentSlot::Weapon);
+        weapon.attack_action_time = -10.0;
+        let mut member = member(vec![weapon]);
+        member.definition.abilities[0].action_time = 30.0;
+
+        let definition = member.battle_definition(&levels());
+
+        assert_eq!(definition.abilities[0].action_time, 30.0);
+    }
+
+    #[test]
+    fn block_fatigue_cost_never_goes_negative() {
+        let mut armour = equipment(EquipmentSlot::Armour);
+        armour.block_fatigue_cost = -10;
+
+        let definition = member(vec![armour]).battle_definition(&levels());
+
+        assert_eq!(definition.abilities[1].fatigue_cost, 0);
+        assert_eq!(definition.abilities[0].fatigue_cost, 5);
+    }
+}
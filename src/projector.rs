@@ -78,6 +78,26 @@ impl Projector {
         }
     }
 
+    /// The area left over after taking `width` off the left.
+    pub fn inset_left(&self, width: f32) -> Projector {
+        Projector {
+            anchor_point: self.anchor_point + self.scale(Vector2::new(width, 0.0)),
+            scale: self.scale,
+            width: self.width - self.scale(width),
+            height: self.height
+        }
+    }
+
+    /// The same area moved down by `y`.
+    pub fn offset_down(&self, y: f32) -> Projector {
+        Projector {
+            anchor_point: self.anchor_point + self.scale(Vector2::new(0.0, y)),
+            scale: self.scale,
+            width: self.width,
+            height: self.height
+        }
+    }
+
     pub fn bottom_left(&self, height: f32) -> Projector {
         Projector {
             anchor_point: self.anchor_point + Vector2::new(0.0, self.height) - self.scale(Vector2::new(0.0, height)),
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path};
+
+use crate::battle::{ClockMode};
+
+pub const UI_SCALES: [f32; 5] = [0.7, 0.9, 1.0, 1.2, 1.5];
+pub const BATTLE_SPEEDS: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+pub const CLOCK_MODES: [ClockMode; 3] = [ClockMode::Active, ClockMode::Wait, ClockMode::Turns];
+
+/// Player preferences, kept between sessions.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ui_scale: f32,
+    /// Multiplies how fast battle time passes, animations included
+    pub battle_speed: f32,
+    pub clock_mode: ClockMode
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ui_scale: 1.0,
+            battle_speed: 1.0,
+            clock_mode: ClockMode::Active
+        }
+    }
+}
+
+impl Settings {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let settings: Self = serde_json::from_reader(BufReader::new(file))?;
+        Ok(settings.snapped())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Swaps anything the inputs don't offer, e.g. from a hand edited file,
+    /// for the default so a 0 speed or huge scale can't break the game.
+    fn snapped(self) -> Self {
+        let defaults = Self::default();
+
+        Self {
+            ui_scale: UI_SCALES[option_index(&UI_SCALES, self.ui_scale, defaults.ui_scale) as usize],
+            battle_speed: BATTLE_SPEEDS[option_index(&BATTLE_SPEEDS, self.battle_speed, defaults.battle_speed) as usize],
+            clock_mode: CLOCK_MODES[option_index(&CLOCK_MODES, self.clock_mode, defaults.clock_mode) as usize]
+        }
+    }
+}
+
+/// Which of `options` is `value`, or `default` if it isn't one of them.
+pub fn option_index<T: PartialEq>(options: &[T], value: T, default: T) -> i16 {
+    options.iter()
+        .position(|option| *option == value)
+        .or_else(|| options.iter().position(|option| *option == default))
+        .unwrap_or(0) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests run side by side, so each writes its own file.
+    fn load(test: &str, json: &str) -> Settings {
+        let path = std::env::temp_dir().join(format!("rpg_battle_{}_{}.json", test, std::process::id()));
+        std::fs::write(&path, json).unwrap();
+        let settings = Settings::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        settings
+    }
+
+    #[test]
+    fn option_index_finds_the_value() {
+        assert_eq!(option_index(&BATTLE_SPEEDS, 1.5, 1.0), 3);
+        assert_eq!(option_index(&CLOCK_MODES, ClockMode::Turns, ClockMode::Active), 2);
+    }
+
+    #[test]
+    fn option_index_falls_back_to_the_default() {
+        assert_eq!(option_index(&UI_SCALES, 1.1, 1.0), 2);
+        assert_eq!(option_index(&BATTLE_SPEEDS, f32::NAN, 1.0), 2);
+    }
+
+    #[test]
+    fn option_index_falls_back_to_the_first_without_a_valid_default() {
+        assert_eq!(option_index(&UI_SCALES, 1.1, 3.0), 0);
+    }
+
+    #[test]
+    fn offered_values_load_as_saved() {
+        let settings = load("offered", r#"{ "ui_scale": 1.5, "battle_speed": 0.5, "clock_mode": "Turns" }"#);
+
+        assert_eq!(settings, Settings { ui_scale: 1.5, battle_speed: 0.5, clock_mode: ClockMode::Turns });
+    }
+
+    #[test]
+    fn out_of_range_values_load_as_the_default() {
+        let settings = load("out_of_range", r#"{ "ui_scale": 40.0, "battle_speed": -1.0, "clock_mode": "Wait" }"#);
+
+        assert_eq!(settings, Settings { ui_scale: 1.0, battle_speed: 1.0, clock_mode: ClockMode::Wait });
+    }
+
+    #[test]
+    fn off_grid_values_load_as_the_default() {
+        let settings = load("off_grid", r#"{ "ui_scale": 1.1, "battle_speed": 0.0 }"#);
+
+        assert_eq!(settings, Settings::default());
+    }
+}
@@ -0,0 +1,36 @@
+use rpg_battle::battle::{Outcome};
+use rpg_battle::battle::replay::{Replay, ReplayPlayer};
+
+/// Plays a recorded battle through and checks it ends the way it did when recorded.
+fn assert_replays(name: &str, expected: Outcome) {
+    let path = format!("{}/tests/replays/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+    let replay = Replay::load(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+
+    assert_eq!(replay.outcome, Some(expected), "{} was recorded with a different outcome", name);
+
+    let mut player = ReplayPlayer::new(replay);
+    let battle = player.play_to_end();
+
+    assert_eq!(battle.outcome(), Some(expected), "{} ended differently on replay", name);
+    assert!(!player.desynced(), "{} commands out of sync", name);
+}
+
+#[test]
+fn two_enemies() {
+    assert_replays("two_enemies", Outcome::Victory);
+}
+
+#[test]
+fn escaped() {
+    assert_replays("escaped", Outcome::Escaped);
+}
+
+#[test]
+fn wait_then_active() {
+    assert_replays("wait_then_active", Outcome::Victory);
+}
+
+#[test]
+fn queued_turns() {
+    assert_replays("queued_turns", Outcome::Victory);
+}